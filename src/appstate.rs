@@ -1,10 +1,16 @@
 use std::sync::Arc;
 
-use crate::cache::{assets_cache::AssetsCache, blocknumbers_cache::BlockNumbers};
-use crate::models::notification::NotificationRepo;
+use crate::cache::{
+    assets_cache::AssetsCache, balances_cache::Balances, blocknumbers_cache::BlockNumbers,
+};
+use crate::handlers::ws::BalanceFeed;
+use crate::models::{commitment::CommitmentRepo, notification::NotificationRepo};
 
 pub struct AppState {
     pub cached_assets: Arc<AssetsCache>,
     pub block_numbers: Arc<BlockNumbers>,
+    pub balances: Arc<Balances>,
+    pub balance_feed: Arc<BalanceFeed>,
     pub notifications: Arc<NotificationRepo>,
+    pub commitments: Arc<CommitmentRepo>,
 }
@@ -1,2 +1,6 @@
 pub mod assets_cache;
+pub mod balances_cache;
+pub mod block_times;
 pub mod blocknumbers_cache;
+pub mod reorgs;
+pub mod rpc_health;
@@ -3,7 +3,6 @@ use std::{collections::HashMap, sync::Arc};
 use crate::{
     handlers::assets::NetworkResponse,
     models::assets::{Network, NetworkType},
-    utils::load_config,
 };
 
 pub struct AssetsCache {
@@ -13,12 +12,10 @@ pub struct AssetsCache {
 }
 
 impl AssetsCache {
-    pub fn new() -> Self {
+    pub fn new(config: &HashMap<String, Network>) -> Self {
         let mut mainnet_assets = HashMap::new();
         let mut testnet_assets = HashMap::new();
         let mut localnet_assets = HashMap::new();
-        let config: HashMap<String, Network> = load_config();
-
         for (identifier, network) in config {
             let network_data = NetworkResponse {
                 chain_id: network.chain_id.clone(),
@@ -0,0 +1,242 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use chrono::Utc;
+use moka::future::{Cache, CacheBuilder};
+use serde::Serialize;
+use tokio::{sync::broadcast, time};
+
+use crate::{
+    cache::blocknumbers_cache::BlockNumbers,
+    chains::ChainAdapter,
+    models::{
+        assets::{Asset, Network, NetworkType},
+        balances::{AssetBalance, BalanceQuery, UtxoAddressBalance},
+    },
+};
+
+pub struct Balances {
+    pub rpcs: Arc<HashMap<String, Vec<String>>>,
+    pub adapters: Arc<HashMap<String, Arc<dyn ChainAdapter>>>,
+    pub networks: Arc<HashMap<String, Network>>,
+    pub mainnet: Cache<String, Vec<AssetBalance>>,
+    pub testnet: Cache<String, Vec<AssetBalance>>,
+    pub localnet: Cache<String, Vec<AssetBalance>>,
+    /// Per-address balances and unspent outputs of UTXO chains, keyed by chain.
+    pub utxos: Cache<String, Vec<UtxoAddressBalance>>,
+    /// Publishes a chain's balances whenever any of its amounts change.
+    pub updates: broadcast::Sender<BalanceUpdate>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BalanceUpdate {
+    #[serde(rename = "networkType")]
+    pub network_type: NetworkType,
+    pub chain: String,
+    pub balances: Vec<AssetBalance>,
+}
+
+/// Compares two snapshots of a chain's balances, ignoring when they were fetched.
+fn same_amounts(previous: &[AssetBalance], current: &[AssetBalance]) -> bool {
+    previous.len() == current.len()
+        && previous.iter().zip(current).all(|(previous, current)| {
+            previous.token_address == current.token_address && previous.balances == current.balances
+        })
+}
+
+fn enabled_assets(network: &Network) -> impl Iterator<Item = &Asset> {
+    network
+        .asset_config
+        .iter()
+        .filter(|asset| !asset.disabled.unwrap_or(false))
+}
+
+/// Builds the balances of every enabled asset from `amounts`, which hold one amount
+/// per filler address for each asset in turn. Assets whose amounts don't fit in a
+/// `u128` are left out.
+fn asset_balances(chain: &str, network: &Network, amounts: &[Option<u128>]) -> Vec<AssetBalance> {
+    let updated_at = Utc::now();
+    enabled_assets(network)
+        .zip(amounts.chunks(network.filler_addresses.len()))
+        .filter_map(|(asset, amounts)| {
+            let total = amounts
+                .iter()
+                .try_fold(0u128, |total, amount| total.checked_add((*amount)?));
+            let Some(total) = total else {
+                eprintln!(
+                    "Skipping balance too large to track chain: {} asset: {}",
+                    chain, asset.symbol
+                );
+                return None;
+            };
+            Some(AssetBalance {
+                symbol: asset.symbol.clone(),
+                decimals: asset.decimals,
+                token_address: asset.token_address.clone(),
+                balances: network
+                    .filler_addresses
+                    .iter()
+                    .cloned()
+                    .zip(amounts.iter().flatten().map(|amount| amount.to_string()))
+                    .collect(),
+                total: total.to_string(),
+                reserved: "0".to_string(),
+                virtual_balance: total.to_string(),
+                updated_at,
+            })
+        })
+        .collect()
+}
+
+/// Balances of a UTXO chain's assets, all of which are its native coin, from the
+/// confirmed balance of each filler address.
+fn utxo_asset_balances(
+    chain: &str,
+    network: &Network,
+    utxos: &[UtxoAddressBalance],
+) -> Vec<AssetBalance> {
+    let amounts: Vec<Option<u128>> = enabled_assets(network)
+        .flat_map(|_| utxos.iter().map(|utxo| Some(utxo.confirmed as u128)))
+        .collect();
+    asset_balances(chain, network, &amounts)
+}
+
+impl Balances {
+    /// Tracks every chain that has filler addresses configured and an adapter able to
+    /// read balances, reusing the rpcs and adapters already set up for block numbers.
+    pub fn new(block_numbers: &BlockNumbers, config: &HashMap<String, Network>) -> Self {
+        let networks: HashMap<String, Network> = config
+            .iter()
+            .filter(|(identifier, network)| {
+                !network.filler_addresses.is_empty()
+                    && block_numbers
+                        .adapters
+                        .get(*identifier)
+                        .is_some_and(|adapter| adapter.supports_balances())
+            })
+            .map(|(identifier, network)| (identifier.clone(), network.clone()))
+            .collect();
+
+        Balances {
+            rpcs: block_numbers.rpcs.clone(),
+            adapters: block_numbers.adapters.clone(),
+            networks: Arc::new(networks),
+            mainnet: CacheBuilder::new(100).build(),
+            testnet: CacheBuilder::new(100).build(),
+            localnet: CacheBuilder::new(100).build(),
+            utxos: CacheBuilder::new(100).build(),
+            updates: broadcast::channel(64).0,
+        }
+    }
+
+    pub fn cache(&self, network_type: &NetworkType) -> &Cache<String, Vec<AssetBalance>> {
+        match network_type {
+            NetworkType::MAINNET => &self.mainnet,
+            NetworkType::TESTNET => &self.testnet,
+            NetworkType::LOCALNET => &self.localnet,
+        }
+    }
+
+    pub async fn start_cron(&self) {
+        let mut interval = time::interval(Duration::from_secs(15));
+
+        loop {
+            interval.tick().await;
+            println!("Fetching balances for all chains");
+
+            self.update_balances().await;
+
+            println!("Finished fetching balances for all chains");
+        }
+    }
+
+    pub async fn update_balances(&self) {
+        let futures = self.networks.iter().map(|(chain, network)| async move {
+            let utxos = self.get_chain_utxos(chain, network).await;
+            // a UTXO fetch already carries each address's confirmed balance, so those
+            // chains aren't queried a second time for their balances
+            let balances = if self.adapters[chain].supports_utxos() {
+                utxos
+                    .as_ref()
+                    .map(|utxos| utxo_asset_balances(chain, network, utxos))
+            } else {
+                self.get_chain_balances(chain, network).await
+            };
+            // keep the last successfully fetched balances if every rpc fails
+            if let Some(balances) = balances {
+                let cache = self.cache(&network.network_type);
+                let changed = cache
+                    .get(chain)
+                    .await
+                    .is_none_or(|previous| !same_amounts(&previous, &balances));
+                cache.insert(chain.clone(), balances.clone()).await;
+                if changed {
+                    // sending only fails when nobody is subscribed
+                    let _ = self.updates.send(BalanceUpdate {
+                        network_type: network.network_type.clone(),
+                        chain: chain.clone(),
+                        balances,
+                    });
+                }
+            }
+            if let Some(utxos) = utxos {
+                self.utxos.insert(chain.clone(), utxos).await;
+            }
+        });
+        futures::future::join_all(futures).await;
+    }
+
+    pub async fn get_chain_balances(
+        &self,
+        chain: &str,
+        network: &Network,
+    ) -> Option<Vec<AssetBalance>> {
+        let adapter = self.adapters.get(chain)?;
+        let rpcs = self.rpcs.get(chain)?;
+        let queries: Vec<BalanceQuery> = enabled_assets(network)
+            .flat_map(|asset| {
+                network.filler_addresses.iter().map(|address| BalanceQuery {
+                    token_address: asset.token_address.clone(),
+                    address: address.clone(),
+                })
+            })
+            .collect();
+
+        for rpc in rpcs {
+            match adapter.fetch_balances(rpc, &queries).await {
+                Ok(amounts) => return Some(asset_balances(chain, network, &amounts)),
+                Err(e) => {
+                    eprintln!("Error fetching balances chain: {} {}", chain, e);
+                    continue;
+                }
+            };
+        }
+        None
+    }
+
+    pub async fn get_chain_utxos(
+        &self,
+        chain: &str,
+        network: &Network,
+    ) -> Option<Vec<UtxoAddressBalance>> {
+        let adapter = self.adapters.get(chain)?;
+        if !adapter.supports_utxos() {
+            return None;
+        }
+        let rpcs = self.rpcs.get(chain)?;
+
+        'rpcs: for rpc in rpcs {
+            let mut utxos = Vec::with_capacity(network.filler_addresses.len());
+            for address in &network.filler_addresses {
+                match adapter.fetch_utxo_balance(rpc, address).await {
+                    Ok(balance) => utxos.push(balance),
+                    Err(e) => {
+                        eprintln!("Error fetching utxos chain: {} {}", chain, e);
+                        continue 'rpcs;
+                    }
+                }
+            }
+            return Some(utxos);
+        }
+        None
+    }
+}
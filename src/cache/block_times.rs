@@ -0,0 +1,159 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+/// How far back height changes are kept for the rolling average.
+const WINDOW: TimeDelta = TimeDelta::minutes(30);
+
+/// A height and the time it was produced, or first seen when the chain doesn't
+/// expose block timestamps.
+type Sample = (DateTime<Utc>, u64);
+
+/// Heights observed per chain, each with the time it was produced, for estimating
+/// the average block time.
+#[derive(Default)]
+pub struct BlockTimeTracker {
+    samples: Mutex<HashMap<String, VecDeque<Sample>>>,
+}
+
+impl BlockTimeTracker {
+    /// Records that `chain` reached `height` at `at`. A height below the last one
+    /// (a reorg or a lagging rpc) restarts the window.
+    pub fn record(&self, chain: &str, height: u64, at: DateTime<Utc>) {
+        let mut samples = self.samples.lock().unwrap();
+        let samples = samples.entry(chain.to_string()).or_default();
+        match samples.back() {
+            Some((_, last)) if height == *last => return,
+            Some((_, last)) if height < *last => samples.clear(),
+            _ => {}
+        }
+        samples.push_back((at, height));
+        while samples
+            .front()
+            .is_some_and(|(first, _)| at - *first > WINDOW)
+        {
+            samples.pop_front();
+        }
+    }
+
+    /// Average seconds per block over the window, once at least two heights were seen.
+    pub fn average_block_time(&self, chain: &str) -> Option<f64> {
+        let samples = self.samples.lock().unwrap();
+        let samples = samples.get(chain)?;
+        let (first_at, first) = samples.front()?;
+        let (last_at, last) = samples.back()?;
+        if last <= first {
+            return None;
+        }
+        let elapsed = (*last_at - *first_at).as_seconds_f64();
+        Some(elapsed / (last - first) as f64)
+    }
+
+    /// The most recent height of `chain` and when it was produced.
+    pub fn latest(&self, chain: &str) -> Option<Sample> {
+        let samples = self.samples.lock().unwrap();
+        samples.get(chain)?.back().copied()
+    }
+}
+
+/// When `target` is expected to be produced, going from `latest` at
+/// `average_block_time` seconds per block. Targets already reached give the time of
+/// `latest`; `None` when the estimate doesn't fit in a timestamp.
+pub fn estimate_time_of(
+    latest: Sample,
+    average_block_time: f64,
+    target: u64,
+) -> Option<DateTime<Utc>> {
+    let (seen_at, height) = latest;
+    let remaining_ms = target.saturating_sub(height) as f64 * average_block_time * 1000.0;
+    Some(remaining_ms)
+        .filter(|remaining_ms| *remaining_ms < i64::MAX as f64)
+        .and_then(|remaining_ms| TimeDelta::try_milliseconds(remaining_ms as i64))
+        .and_then(|remaining| seen_at.checked_add_signed(remaining))
+}
+
+/// The height expected at `at`, before or after `latest`, never below 0.
+pub fn estimate_height_at(latest: Sample, average_block_time: f64, at: DateTime<Utc>) -> u64 {
+    let (seen_at, height) = latest;
+    let blocks = (at - seen_at).as_seconds_f64() / average_block_time;
+    (height as f64 + blocks).floor().max(0.0) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHAIN: &str = "ethereum_sepolia";
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    #[test]
+    fn needs_two_heights_to_estimate() {
+        let tracker = BlockTimeTracker::default();
+        assert_eq!(tracker.average_block_time(CHAIN), None);
+        assert_eq!(tracker.latest(CHAIN), None);
+
+        tracker.record(CHAIN, 100, at(0));
+        assert_eq!(tracker.average_block_time(CHAIN), None);
+        // a repeated height is no new sample
+        tracker.record(CHAIN, 100, at(12));
+        assert_eq!(tracker.average_block_time(CHAIN), None);
+        assert_eq!(tracker.latest(CHAIN), Some((at(0), 100)));
+    }
+
+    #[test]
+    fn averages_a_steady_rate() {
+        let tracker = BlockTimeTracker::default();
+        for block in 0..10 {
+            tracker.record(CHAIN, 100 + block, at(block as i64 * 12));
+        }
+        assert_eq!(tracker.average_block_time(CHAIN), Some(12.0));
+        // skipped heights count as the blocks they cover
+        tracker.record(CHAIN, 112, at(9 * 12 + 36));
+        assert_eq!(tracker.average_block_time(CHAIN), Some(12.0));
+        assert_eq!(tracker.latest(CHAIN), Some((at(144), 112)));
+    }
+
+    #[test]
+    fn restarts_after_a_lower_height_and_drops_old_samples() {
+        let tracker = BlockTimeTracker::default();
+        tracker.record(CHAIN, 100, at(0));
+        tracker.record(CHAIN, 101, at(12));
+        tracker.record(CHAIN, 99, at(24));
+        assert_eq!(tracker.average_block_time(CHAIN), None);
+
+        tracker.record(CHAIN, 100, at(30));
+        // the first sample falls out of the window
+        let later = WINDOW.num_seconds() + 40;
+        tracker.record(CHAIN, 200, at(later));
+        tracker.record(CHAIN, 201, at(later + 2));
+        assert_eq!(tracker.average_block_time(CHAIN), Some(2.0));
+    }
+
+    #[test]
+    fn estimates_the_time_of_a_height() {
+        let latest = (at(0), 100);
+        assert_eq!(estimate_time_of(latest, 12.0, 110), Some(at(120)));
+        // heights already reached are due at the latest height's time
+        assert_eq!(estimate_time_of(latest, 12.0, 100), Some(at(0)));
+        assert_eq!(estimate_time_of(latest, 12.0, 50), Some(at(0)));
+        assert_eq!(estimate_time_of(latest, 12.0, u64::MAX), None);
+    }
+
+    #[test]
+    fn estimates_the_height_at_a_time() {
+        let latest = (at(0), 100);
+        assert_eq!(estimate_height_at(latest, 12.0, at(0)), 100);
+        assert_eq!(estimate_height_at(latest, 12.0, at(125)), 110);
+        assert_eq!(estimate_height_at(latest, 12.0, at(-120)), 90);
+        // partial blocks are rounded down on both sides
+        assert_eq!(estimate_height_at(latest, 12.0, at(-1)), 99);
+        // never below genesis
+        assert_eq!(estimate_height_at(latest, 12.0, at(-100_000)), 0);
+    }
+}
@@ -1,339 +1,832 @@
-use std::{collections::HashMap, error::Error, sync::Arc, time::Duration};
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
+use chrono::{DateTime, Utc};
 use moka::future::{Cache, CacheBuilder};
-use serde_json::json;
-use tokio::time;
+use rand::Rng;
+use serde::Serialize;
+use tokio::{sync::broadcast, time};
 
 use crate::{
-    models::assets::{Network, NetworkType},
-    utils::load_config,
+    cache::{block_times::BlockTimeTracker, reorgs::ReorgTracker, rpc_health::RpcHealthTracker},
+    chains::{
+        http::HttpClient,
+        subscriptions::{TipFeed, WsEndpoint},
+        AdapterResult, BlockTag, ChainAdapter, LayerHeights, SlotHeights,
+    },
+    models::{
+        assets::{Network, NetworkType, QuorumConfig, QuorumStrategy},
+        block_history::{BlockHistoryRepo, RETENTION},
+    },
+    utils::rpc_host,
 };
+
+/// Fraction of a chain's poll interval by which each fetch is randomly moved.
+const POLL_JITTER: f64 = 0.1;
+/// Wait before subscribing again once every websocket of a chain has dropped.
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+/// How often sampled block numbers are written to the history table.
+const HISTORY_FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+/// How often history older than the retention period is deleted.
+const HISTORY_PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+
 pub struct BlockNumbers {
     pub rpcs: Arc<HashMap<String, Vec<String>>>,
+    pub adapters: Arc<HashMap<String, Arc<dyn ChainAdapter>>>,
+    pub quorums: Arc<HashMap<String, QuorumConfig>>,
+    pub health: RpcHealthTracker,
+    /// How long a chain's block number may go without a successful fetch before it
+    /// is reported as stale.
+    pub stale_after: Arc<HashMap<String, Duration>>,
+    /// How often each chain's block number is fetched.
+    pub poll_intervals: Arc<HashMap<String, Duration>>,
+    /// Http urls and websockets of the rpcs that have one, for chains whose tip can be
+    /// pushed instead of polled.
+    pub tip_feeds: Arc<HashMap<String, Vec<(String, WsEndpoint)>>>,
+    /// Http url of the rpc whose websocket is currently pushing each chain's tip.
+    pub subscribed: Cache<String, String>,
     pub mainnet: Cache<String, u64>,
     pub testnet: Cache<String, u64>,
     pub localnet: Cache<String, u64>,
-    pub client: reqwest::Client,
+    /// When and where each chain's block number was last fetched successfully.
+    pub freshness: Cache<String, Freshness>,
+    /// Safe and finalized heights of each chain, alongside the latest heights above.
+    pub finality: Cache<String, FinalityHeights>,
+    /// Both the L1 and L2 heights of L2 chains, whichever of them is published above.
+    pub layer_heights: Cache<String, LayerHeights>,
+    /// Both the slot and the block height of slot-based chains.
+    pub slot_heights: Cache<String, SlotHeights>,
+    /// Number of the block being built on top of the latest one, where exposed.
+    pub pending: Cache<String, u64>,
+    /// Tip headers of every chain, used to detect reorgs.
+    pub reorgs: ReorgTracker,
+    /// Recent height changes of every chain, used to estimate block times.
+    pub block_times: BlockTimeTracker,
+    /// Stores every observed change of a chain's block number.
+    pub history: Arc<BlockHistoryRepo>,
+    /// Publishes every observed change of a chain's block number.
+    pub updates: broadcast::Sender<BlockNumberUpdate>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockNumberUpdate {
+    #[serde(rename = "networkType")]
+    pub network_type: NetworkType,
+    pub chain: String,
+    #[serde(rename = "blockNumber")]
+    pub block_number: u64,
 }
 
-#[derive(PartialEq, Debug)]
-pub enum SupportedChains {
-    ETHEREUM,
-    ARBITRUM,
-    SOLANA,
-    STARKNET,
-    BITCOIN,
+#[derive(Debug, Clone)]
+pub struct Freshness {
+    pub updated_at: DateTime<Utc>,
+    /// Host of the rpc that reported the block number.
+    pub source_rpc: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FinalityHeights {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub safe: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finalized: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockNumberDetails {
+    #[serde(rename = "blockNumber")]
+    pub block_number: u64,
+    #[serde(rename = "safeBlockNumber", skip_serializing_if = "Option::is_none")]
+    pub safe_block_number: Option<u64>,
+    #[serde(
+        rename = "finalizedBlockNumber",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub finalized_block_number: Option<u64>,
+    #[serde(rename = "pendingBlockNumber", skip_serializing_if = "Option::is_none")]
+    pub pending_block_number: Option<u64>,
+    #[serde(flatten)]
+    pub layer_heights: Option<LayerHeights>,
+    #[serde(flatten)]
+    pub slot_heights: Option<SlotHeights>,
+    #[serde(rename = "blockHash", skip_serializing_if = "Option::is_none")]
+    pub block_hash: Option<String>,
+    #[serde(rename = "parentHash", skip_serializing_if = "Option::is_none")]
+    pub parent_hash: Option<String>,
+    #[serde(rename = "blockTimestamp", skip_serializing_if = "Option::is_none")]
+    pub block_timestamp: Option<u64>,
+    #[serde(rename = "lastUpdatedAt")]
+    pub last_updated_at: Option<DateTime<Utc>>,
+    #[serde(rename = "sourceRpc")]
+    pub source_rpc: Option<String>,
+    pub stale: bool,
 }
 
 impl BlockNumbers {
-    pub async fn new() -> Self {
+    /// Fails when a network's http or websocket config is invalid, e.g. a malformed
+    /// header or an unsupported proxy, rather than serving it without its rpcs.
+    pub async fn new(
+        history: Arc<BlockHistoryRepo>,
+        configs: &HashMap<String, Network>,
+    ) -> anyhow::Result<Self> {
         let testnet = CacheBuilder::new(100).build();
         let mainnet = CacheBuilder::new(100).build();
         let localnet = CacheBuilder::new(100).build();
+        let freshness: Cache<String, Freshness> = CacheBuilder::new(100).build();
+        // start from the last stored block numbers rather than 0
+        let stored: HashMap<(String, String), (u64, Freshness)> =
+            match history.get_latest_block_numbers().await {
+                Ok(records) => records
+                    .into_iter()
+                    .map(|record| {
+                        let freshness = Freshness {
+                            updated_at: record.fetched_at,
+                            source_rpc: record.source_rpc,
+                        };
+                        (
+                            (record.chain, record.network_type),
+                            (record.height as u64, freshness),
+                        )
+                    })
+                    .collect(),
+                Err(e) => {
+                    eprintln!("Error loading stored block numbers: {}", e);
+                    HashMap::new()
+                }
+            };
         let mut rpcs = HashMap::new();
-        let configs: HashMap<String, Network> = load_config();
+        let mut adapters = HashMap::new();
+        let mut quorums = HashMap::new();
+        let mut stale_after = HashMap::new();
+        let mut poll_intervals = HashMap::new();
+        let mut tip_feeds = HashMap::new();
         for (identifier, config) in configs {
+            let invalid = |e| anyhow::anyhow!("Invalid http config chain: {} {}", identifier, e);
+            let client = HttpClient::new(&config.http, &config.rpcs).map_err(invalid)?;
+            let adapter = config
+                .chain_family
+                .adapter(client, config)
+                .map_err(invalid)?;
+            // a pushed tip comes from a single rpc, so quorum chains are always polled
+            if adapter.tip_subscription().is_some() && config.rpc_quorum.is_none() {
+                let mut feeds = Vec::new();
+                for rpc in &config.rpcs {
+                    if let Some(ws) = WsEndpoint::new(rpc, &config.http).map_err(invalid)? {
+                        feeds.push((rpc.url.clone(), ws));
+                    }
+                }
+                if !feeds.is_empty() {
+                    tip_feeds.insert(identifier.clone(), feeds);
+                }
+            }
+            adapters.insert(identifier.clone(), adapter);
+            if let Some(quorum) = config.rpc_quorum.clone() {
+                quorums.insert(identifier.clone(), quorum);
+            }
+            stale_after.insert(identifier.clone(), config.stale_after());
+            poll_intervals.insert(
+                identifier.clone(),
+                Duration::from_millis(config.poll_interval_ms),
+            );
+            let rpc_urls: Vec<String> = config.rpcs.iter().map(|rpc| rpc.url.clone()).collect();
+            let initial =
+                match stored.get(&(identifier.clone(), config.network_type.as_str().to_string())) {
+                    Some((height, stored_freshness)) => {
+                        freshness
+                            .insert(identifier.clone(), stored_freshness.clone())
+                            .await;
+                        *height
+                    }
+                    None => 0,
+                };
             match config.network_type {
                 NetworkType::TESTNET => {
-                    testnet.insert(identifier.clone(), 0).await;
-                    rpcs.insert(identifier.clone(), config.rpcs.clone());
+                    testnet.insert(identifier.clone(), initial).await;
+                    rpcs.insert(identifier.clone(), rpc_urls.clone());
                 }
                 NetworkType::MAINNET => {
-                    mainnet.insert(identifier.clone(), 0).await;
-                    rpcs.insert(identifier.clone(), config.rpcs.clone());
+                    mainnet.insert(identifier.clone(), initial).await;
+                    rpcs.insert(identifier.clone(), rpc_urls.clone());
                 }
                 NetworkType::LOCALNET => {
-                    localnet.insert(identifier.clone(), 0).await;
-                    rpcs.insert(identifier.clone(), config.rpcs.clone());
+                    localnet.insert(identifier.clone(), initial).await;
+                    rpcs.insert(identifier.clone(), rpc_urls.clone());
                 }
             }
         }
-        BlockNumbers {
+        Ok(BlockNumbers {
             rpcs: Arc::new(rpcs),
+            adapters: Arc::new(adapters),
+            quorums: Arc::new(quorums),
+            health: RpcHealthTracker::default(),
+            stale_after: Arc::new(stale_after),
+            poll_intervals: Arc::new(poll_intervals),
+            tip_feeds: Arc::new(tip_feeds),
+            subscribed: CacheBuilder::new(100).build(),
             mainnet,
             testnet,
             localnet,
-            client: reqwest::Client::new(),
+            freshness,
+            finality: CacheBuilder::new(100).build(),
+            layer_heights: CacheBuilder::new(100).build(),
+            slot_heights: CacheBuilder::new(100).build(),
+            pending: CacheBuilder::new(100).build(),
+            reorgs: ReorgTracker::default(),
+            block_times: BlockTimeTracker::default(),
+            history,
+            updates: broadcast::channel(1024).0,
+        })
+    }
+
+    /// The height of `chain` counted in its own blocks: the L2 height of L2 chains,
+    /// whichever height they publish, and the published block number otherwise.
+    pub async fn own_height(&self, chain: &str, network_type: &NetworkType) -> Option<u64> {
+        if self
+            .adapters
+            .get(chain)
+            .is_some_and(|adapter| adapter.is_layer2())
+        {
+            return self
+                .layer_heights
+                .get(chain)
+                .await
+                .map(|heights| heights.l2)
+                .filter(|blocknumber| *blocknumber > 0);
         }
+        self.cache(network_type)
+            .get(chain)
+            .await
+            .filter(|blocknumber| *blocknumber > 0)
     }
-    pub async fn get_chain_type(&self, chain: Arc<String>) -> SupportedChains {
-        if chain.contains("arbitrum") {
-            SupportedChains::ARBITRUM
-        } else if chain.contains("solana") {
-            SupportedChains::SOLANA
-        } else if chain.contains("starknet") {
-            SupportedChains::STARKNET
-        } else if chain.contains("bitcoin") {
-            SupportedChains::BITCOIN
-        } else {
-            SupportedChains::ETHEREUM
+
+    pub fn cache(&self, network_type: &NetworkType) -> &Cache<String, u64> {
+        match network_type {
+            NetworkType::MAINNET => &self.mainnet,
+            NetworkType::TESTNET => &self.testnet,
+            NetworkType::LOCALNET => &self.localnet,
         }
     }
-    pub async fn get_chain_blocknumber(
-        &self,
-        chain: Arc<String>,
-        network_type: NetworkType,
-    ) -> u64 {
-        let chain_name = self.get_chain_type(chain.clone()).await;
-        let rpcs = self.rpcs.get(&*chain).unwrap();
-        match chain_name {
-            SupportedChains::BITCOIN => {
-                for rpc in rpcs {
-                    match self.get_btc_block_number(rpc.to_string()).await {
-                        Ok(blocknumber) => {
-                            return blocknumber;
-                        }
-                        Err(e) => {
-                            eprintln!("Error fetching block number chain: {} {}", chain, e);
-                            continue;
-                        }
-                    };
-                }
-            }
-            SupportedChains::ARBITRUM => {
-                for rpc in rpcs {
-                    let result = match network_type {
-                        NetworkType::LOCALNET => {
-                            self.fetch_ethereum_block_number(&rpc.to_string()).await
-                        }
-                        _ => self.fetch_arbitrum_l1_block_number(&rpc.to_string()).await,
-                    };
 
-                    match result {
-                        Ok(blocknumber) => return blocknumber,
-                        Err(e) => {
-                            eprintln!("Error fetching block number chain: {} {}", chain, e);
-                            continue;
-                        }
-                    };
+    /// Fetches the block number of `chain` along with the rpc that reported it.
+    /// Returns `None` when no rpc answered (or no quorum was reached), in which case
+    /// the cached value is kept and eventually reported as stale.
+    pub async fn get_chain_blocknumber(&self, chain: Arc<String>) -> Option<(u64, String)> {
+        let adapter = self.adapters.get(&*chain).unwrap();
+        self.probe_half_open(&chain, adapter.as_ref()).await;
+        // healthiest rpcs first, skipping those with an open circuit
+        let rpcs = self
+            .health
+            .ordered_rpcs(&chain, self.rpcs.get(&*chain).unwrap());
+        if let Some(quorum) = self.quorums.get(&*chain) {
+            return self
+                .get_quorum_blocknumber(&chain, adapter.as_ref(), &rpcs, quorum)
+                .await;
+        }
+        for rpc in &rpcs {
+            let (result, latency) = timed_fetch_tip(adapter.as_ref(), rpc).await;
+            match result {
+                Ok(blocknumber) => {
+                    self.health.record_success(&chain, rpc, latency);
+                    return Some((blocknumber, rpc.clone()));
                 }
-            }
-            SupportedChains::STARKNET => {
-                for rpc in rpcs {
-                    match self.fetch_starknet_block_number(&rpc.to_string()).await {
-                        Ok(blocknumber) => {
-                            return blocknumber;
-                        }
-                        Err(e) => {
-                            eprintln!("Error fetching block number chain: {} {}", chain, e);
-                            continue;
-                        }
-                    };
+                Err(e) => {
+                    eprintln!("Error fetching block number chain: {} {}", chain, e);
+                    self.health.record_failure(&chain, rpc, e.to_string());
+                    continue;
                 }
+            };
+        }
+        None
+    }
+
+    /// Probes the rpcs of `chain` whose circuit backoff has elapsed, closing the
+    /// circuit of those that answer and extending the backoff of the others.
+    async fn probe_half_open(&self, chain: &str, adapter: &dyn ChainAdapter) {
+        let rpcs = self
+            .health
+            .half_open_rpcs(chain, self.rpcs.get(chain).unwrap());
+        let probes = rpcs.iter().map(|rpc| async move {
+            let started = Instant::now();
+            (adapter.health_probe(rpc).await, started.elapsed())
+        });
+        let results = futures::future::join_all(probes).await;
+        for (rpc, (result, latency)) in rpcs.iter().zip(results) {
+            match result {
+                Ok(()) => self.health.record_success(chain, rpc, latency),
+                Err(e) => self.health.record_failure(chain, rpc, e.to_string()),
             }
-            SupportedChains::SOLANA => {
-                for rpc in rpcs {
-                    match self.fetch_solana_block_number(&rpc.to_string()).await {
-                        Ok(blocknumber) => return blocknumber,
-                        Err(e) => {
-                            eprintln!("Error fetching block number chain: {} {}", chain, e);
-                            continue;
-                        }
-                    };
+        }
+    }
+
+    /// Queries every rpc of `chain` concurrently and resolves their heights according
+    /// to `quorum`. Returns `None` when too few rpcs agree.
+    async fn get_quorum_blocknumber(
+        &self,
+        chain: &str,
+        adapter: &dyn ChainAdapter,
+        rpcs: &[String],
+        quorum: &QuorumConfig,
+    ) -> Option<(u64, String)> {
+        let results =
+            futures::future::join_all(rpcs.iter().map(|rpc| timed_fetch_tip(adapter, rpc))).await;
+
+        let mut reported = Vec::new();
+        let mut latencies = HashMap::new();
+        for (rpc, (result, latency)) in rpcs.iter().zip(results) {
+            match result {
+                Ok(blocknumber) => {
+                    reported.push((rpc.as_str(), blocknumber));
+                    latencies.insert(rpc.as_str(), latency);
                 }
-            }
-            _ => {
-                for rpc in rpcs {
-                    println!("Fetching block number for chain: {}", chain);
-                    match self.fetch_ethereum_block_number(&rpc.to_string()).await {
-                        Ok(blocknumber) => {
-                            return blocknumber;
-                        }
-                        Err(e) => {
-                            eprintln!("Error fetching block number chain: {} {}", chain, e);
-                            continue;
-                        }
-                    };
+                Err(e) => {
+                    eprintln!("Error fetching block number chain: {} {}", chain, e);
+                    self.health.record_failure(chain, rpc, e.to_string());
                 }
             }
         }
-        // fallback on failure to fetch blocknumber is to return the last successfull fetched value, if there is no value, return 0
-        match network_type {
-            NetworkType::MAINNET => {
-                return self.mainnet.get(&*chain).await.unwrap_or(0);
-            }
-            NetworkType::TESTNET => {
-                return self.testnet.get(&*chain).await.unwrap_or(0);
-            }
-            NetworkType::LOCALNET => {
-                return self.localnet.get(&*chain).await.unwrap_or(0);
+
+        let resolution = resolve_quorum(&reported, quorum);
+        for (rpc, blocknumber) in &resolution.divergent {
+            eprintln!(
+                "RPC disagrees with quorum chain: {} rpc: {} reported {} expected {:?}",
+                chain,
+                rpc_host(rpc),
+                blocknumber,
+                resolution.blocknumber
+            );
+            self.health.record_divergence(chain, rpc);
+        }
+        // a divergent rpc still answered, so it only counts against its health when
+        // the request itself fails
+        for (rpc, _) in &reported {
+            self.health.record_success(chain, rpc, latencies[rpc]);
+        }
+        let blocknumber = resolution.blocknumber?;
+        let source_rpc = reported
+            .iter()
+            .find(|(_, height)| *height == blocknumber)
+            .map(|(rpc, _)| rpc.to_string())?;
+        Some((blocknumber, source_rpc))
+    }
+
+    /// Caches `blocknumber` for `chain` as fetched from `source_rpc` and notifies
+    /// subscribers if it changed.
+    pub async fn set_chain_blocknumber(
+        &self,
+        chain: &str,
+        network_type: NetworkType,
+        blocknumber: u64,
+        source_rpc: &str,
+    ) {
+        let fetched_at = Utc::now();
+        let source_rpc = rpc_host(source_rpc);
+        self.freshness
+            .insert(
+                chain.to_string(),
+                Freshness {
+                    updated_at: fetched_at,
+                    source_rpc: source_rpc.clone(),
+                },
+            )
+            .await;
+        let cache = self.cache(&network_type);
+        if cache.get(chain).await == Some(blocknumber) {
+            return;
+        }
+        cache.insert(chain.to_string(), blocknumber).await;
+        // chains with headers are sampled at their block timestamps in `update_header`
+        if !self.adapters[chain].supports_headers() {
+            self.block_times.record(chain, blocknumber, fetched_at);
+        }
+        self.history.record_block_number(
+            chain,
+            &network_type,
+            blocknumber,
+            &source_rpc,
+            fetched_at,
+        );
+        // sending only fails when nobody is subscribed
+        let _ = self.updates.send(BlockNumberUpdate {
+            network_type,
+            chain: chain.to_string(),
+            block_number: blocknumber,
+        });
+    }
+
+    /// Heights of every chain of `network_type` at the given finality. Chains whose
+    /// safe or finalized height could not be fetched yet are left out.
+    pub async fn heights(&self, network_type: &NetworkType, tag: BlockTag) -> HashMap<String, u64> {
+        let mut heights = HashMap::new();
+        for (chain, block_number) in self.cache(network_type).iter() {
+            let height = match tag {
+                BlockTag::LATEST => Some(block_number),
+                BlockTag::SAFE => self.finality.get(&*chain).await.and_then(|f| f.safe),
+                BlockTag::FINALIZED => self.finality.get(&*chain).await.and_then(|f| f.finalized),
+            };
+            if let Some(height) = height {
+                heights.insert((*chain).clone(), height);
             }
         }
+        heights
     }
 
-    pub async fn start_cron(&self) {
-        let mut interval = time::interval(Duration::from_secs(5));
-        interval.tick().await;
+    /// Fetches the safe and finalized heights of `chain` from `rpc`. A height that
+    /// fails to fetch keeps its previous value.
+    pub async fn update_finality(&self, chain: &str, rpc: &str) {
+        let adapter = self.adapters.get(chain).unwrap();
+        let (safe, finalized) = futures::join!(
+            adapter.fetch_by_tag(rpc, BlockTag::SAFE),
+            adapter.fetch_by_tag(rpc, BlockTag::FINALIZED)
+        );
+        let mut heights = self.finality.get(chain).await.unwrap_or_default();
+        match safe {
+            Ok(safe) => heights.safe = Some(safe),
+            Err(e) => eprintln!("Error fetching safe block number chain: {} {}", chain, e),
+        }
+        match finalized {
+            Ok(finalized) => heights.finalized = Some(finalized),
+            Err(e) => eprintln!(
+                "Error fetching finalized block number chain: {} {}",
+                chain, e
+            ),
+        }
+        self.finality.insert(chain.to_string(), heights).await;
+    }
 
-        loop {
-            interval.tick().await;
-            println!("Fetching block numbers for all chains");
+    /// Fetches both heights of an L2 `chain` from `rpc`.
+    pub async fn update_layer_heights(&self, chain: &str, rpc: &str) {
+        let adapter = self.adapters.get(chain).unwrap();
+        if !adapter.is_layer2() {
+            return;
+        }
+        match adapter.fetch_layer_heights(rpc).await {
+            Ok(heights) => self.layer_heights.insert(chain.to_string(), heights).await,
+            Err(e) => eprintln!("Error fetching layer heights chain: {} {}", chain, e),
+        }
+    }
 
-            self.update_block_numbers().await;
+    /// Fetches the other height of a slot-based `chain` from `rpc` to pair with the
+    /// published `blocknumber`.
+    pub async fn update_slot_heights(&self, chain: &str, rpc: &str, blocknumber: u64) {
+        let adapter = self.adapters.get(chain).unwrap();
+        if !adapter.supports_slot_heights() {
+            return;
+        }
+        match adapter.fetch_slot_heights(rpc, blocknumber).await {
+            Ok(heights) => self.slot_heights.insert(chain.to_string(), heights).await,
+            Err(e) => eprintln!("Error fetching slot heights chain: {} {}", chain, e),
+        }
+    }
 
-            println!("Finished fetching block numbers for all chains");
+    /// Fetches the pending block number of `chain` from `rpc`.
+    pub async fn update_pending(&self, chain: &str, rpc: &str) {
+        let adapter = self.adapters.get(chain).unwrap();
+        if !adapter.supports_pending() {
+            return;
+        }
+        match adapter.fetch_pending(rpc).await {
+            Ok(pending) => self.pending.insert(chain.to_string(), pending).await,
+            Err(e) => eprintln!("Error fetching pending block number chain: {} {}", chain, e),
         }
     }
 
-    pub async fn update_block_numbers(&self) {
-        // Create three separate futures for each network type
-        let mainnet_future = async {
-            let mut futures = Vec::new();
-            println!("Fetching MAINNET blocknumbers");
-            for data in self.mainnet.iter() {
-                let chain = data.0.clone();
-
-                // Spawn a task for each chain in mainnet
-                futures.push(async move {
-                    let blocknumber = self
-                        .get_chain_blocknumber(chain.clone(), NetworkType::MAINNET)
-                        .await;
-                    self.mainnet.insert((*chain).clone(), blocknumber).await;
-                });
+    /// Fetches the header of `chain`'s tip at `height` and compares it with the headers
+    /// seen on earlier ticks, recording a reorg when any of them was replaced.
+    pub async fn update_header(
+        &self,
+        chain: &str,
+        network_type: NetworkType,
+        rpc: &str,
+        height: u64,
+    ) {
+        let adapter = self.adapters.get(chain).unwrap();
+        if !adapter.supports_headers() {
+            return;
+        }
+        let tip = match adapter.fetch_header(rpc, height).await {
+            Ok(tip) => tip,
+            Err(e) => {
+                eprintln!("Error fetching block header chain: {} {}", chain, e);
+                return;
             }
-            // Wait for all mainnet chain updates to complete
-            futures::future::join_all(futures).await;
         };
+        if let Some(produced_at) = DateTime::from_timestamp(tip.timestamp as i64, 0) {
+            self.block_times.record(chain, tip.number, produced_at);
+        }
+
+        let fetch = |height| adapter.fetch_header(rpc, height);
+        match self.reorgs.observe(chain, network_type, tip, fetch).await {
+            Ok(Some(event)) => eprintln!(
+                "Reorg detected chain: {} depth: {} at height {}",
+                chain, event.depth, event.fork_height
+            ),
+            Ok(None) => {}
+            Err(e) => eprintln!("Error fetching block header chain: {} {}", chain, e),
+        }
+    }
 
-        let testnet_future = async {
-            let mut futures = Vec::new();
-            println!("Fetching TESTNET blocknumbers");
-            for data in self.testnet.iter() {
-                let chain = data.0.clone();
-                // Spawn a task for each chain in testnet
-                futures.push(async move {
-                    let blocknumber = self
-                        .get_chain_blocknumber(chain.clone(), NetworkType::TESTNET)
-                        .await;
-                    self.testnet.insert((*chain).clone(), blocknumber).await;
-                });
+    /// Block numbers of `network_type` with when and where they were last fetched.
+    pub async fn details(&self, network_type: &NetworkType) -> HashMap<String, BlockNumberDetails> {
+        let now = Utc::now();
+        let mut details = HashMap::new();
+        for (chain, block_number) in self.cache(network_type).iter() {
+            let freshness = self.freshness.get(&*chain).await;
+            let finality = self.finality.get(&*chain).await.unwrap_or_default();
+            let header = self.reorgs.header(&chain, block_number);
+            let layer_heights = self.layer_heights.get(&*chain).await;
+            let slot_heights = self.slot_heights.get(&*chain).await;
+            let pending = self.pending.get(&*chain).await;
+            let stale_after = self
+                .stale_after
+                .get(&*chain)
+                .copied()
+                .unwrap_or(Duration::MAX);
+            // a chain that was never fetched successfully is stale from the start
+            let stale = freshness.as_ref().is_none_or(|freshness| {
+                (now - freshness.updated_at)
+                    .to_std()
+                    .is_ok_and(|age| age > stale_after)
+            });
+            details.insert(
+                (*chain).clone(),
+                BlockNumberDetails {
+                    block_number,
+                    safe_block_number: finality.safe,
+                    finalized_block_number: finality.finalized,
+                    pending_block_number: pending,
+                    layer_heights,
+                    slot_heights,
+                    block_hash: header.as_ref().map(|header| header.hash.clone()),
+                    parent_hash: header.as_ref().map(|header| header.parent_hash.clone()),
+                    block_timestamp: header.map(|header| header.timestamp),
+                    last_updated_at: freshness.as_ref().map(|freshness| freshness.updated_at),
+                    source_rpc: freshness.map(|freshness| freshness.source_rpc),
+                    stale,
+                },
+            );
+        }
+        details
+    }
+
+    /// Polls every chain on its own interval, while following the tips of chains with
+    /// websocket rpcs as they are pushed. Each chain runs in its own loops, so a slow
+    /// or unreachable chain only delays its own next fetch.
+    pub async fn start_cron(&self) {
+        let chains = [
+            (&self.mainnet, NetworkType::MAINNET),
+            (&self.testnet, NetworkType::TESTNET),
+            (&self.localnet, NetworkType::LOCALNET),
+        ]
+        .into_iter()
+        .flat_map(|(cache, network_type)| {
+            cache
+                .iter()
+                .map(move |(chain, _)| (chain, network_type.clone()))
+        });
+        let polls: Vec<_> = chains
+            .map(|(chain, network_type)| async move {
+                futures::join!(
+                    self.poll_chain(chain.clone(), network_type.clone()),
+                    self.follow_chain(chain, network_type)
+                )
+            })
+            .collect();
+        futures::join!(futures::future::join_all(polls), self.write_history());
+    }
+
+    /// Writes the sampled block numbers every `HISTORY_FLUSH_INTERVAL` and deletes
+    /// those older than the retention period every `HISTORY_PRUNE_INTERVAL`.
+    async fn write_history(&self) {
+        let mut flush = time::interval(HISTORY_FLUSH_INTERVAL);
+        let mut prune = time::interval(HISTORY_PRUNE_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = flush.tick() => {
+                    if let Err(e) = self.history.flush().await {
+                        eprintln!("Error storing block numbers: {}", e);
+                    }
+                }
+                _ = prune.tick() => {
+                    if let Err(e) = self.history.prune(Utc::now() - RETENTION).await {
+                        eprintln!("Error pruning block number history: {}", e);
+                    }
+                }
             }
-            // Wait for all testnet chain updates to complete
-            futures::future::join_all(futures).await;
-        };
+        }
+    }
+
+    /// Refreshes `chain` forever, every `pollIntervalMs` give or take a random jitter
+    /// so that chains sharing an rpc provider don't all hit it at once.
+    async fn poll_chain(&self, chain: Arc<String>, network_type: NetworkType) {
+        let interval = *self.poll_intervals.get(&*chain).unwrap();
+        // spread the first fetches over one interval instead of starting them together
+        let mut next = time::Instant::now() + interval.mul_f64(rand::thread_rng().gen());
 
-        let localnet_future = async {
-            let mut futures = Vec::new();
-            println!("Fetching LOCALNET blocknumbers");
-            for data in self.localnet.iter() {
-                let chain = data.0.clone();
-                // Spawn a task for each chain in localnet
-                futures.push(async move {
-                    let blocknumber = self
-                        .get_chain_blocknumber(chain.clone(), NetworkType::LOCALNET)
-                        .await;
-                    self.localnet.insert((*chain).clone(), blocknumber).await;
-                });
+        loop {
+            time::sleep_until(next).await;
+            match self.subscribed.get(&*chain).await {
+                // the tip is pushed over a websocket, so only refresh what depends on it
+                Some(source_rpc) => {
+                    self.refresh_tip_details(&chain, network_type.clone(), &source_rpc)
+                        .await
+                }
+                None => {
+                    self.refresh_chain(chain.clone(), network_type.clone())
+                        .await
+                }
             }
-            // Wait for all localnet chain updates to complete
-            futures::future::join_all(futures).await;
+            // schedule from the previous start so the interval doesn't drift by the
+            // fetch time; a fetch that overran its interval is followed immediately
+            let jitter = rand::thread_rng().gen_range(-POLL_JITTER..=POLL_JITTER);
+            next = (next + interval.mul_f64(1.0 + jitter)).max(time::Instant::now());
+        }
+    }
+
+    /// Follows `chain`'s tip over the websockets of its rpcs, moving on to the next
+    /// rpc whenever a socket drops. While no socket is up, `poll_chain` fetches the tip
+    /// over http as usual.
+    async fn follow_chain(&self, chain: Arc<String>, network_type: NetworkType) {
+        let Some(endpoints) = self.tip_feeds.get(&*chain) else {
+            return;
         };
+        let subscription = self
+            .adapters
+            .get(&*chain)
+            .unwrap()
+            .tip_subscription()
+            .unwrap();
+        // a socket that stays silent for as long as a chain may go without an update
+        // is as good as dropped
+        let idle_timeout = *self.stale_after.get(&*chain).unwrap();
 
-        // Execute all three network futures concurrently
-        futures::join!(mainnet_future, testnet_future, localnet_future);
+        loop {
+            for (rpc, endpoint) in endpoints {
+                let ws = endpoint.url();
+                let mut feed =
+                    match TipFeed::subscribe(endpoint, subscription.clone(), idle_timeout).await {
+                        Ok(feed) => feed,
+                        Err(e) => {
+                            eprintln!(
+                                "Error subscribing to tips chain: {} rpc: {} {}",
+                                chain,
+                                rpc_host(ws),
+                                e
+                            );
+                            continue;
+                        }
+                    };
+                println!("Following tips chain: {} rpc: {}", chain, rpc_host(ws));
+                self.subscribed.insert(chain.to_string(), rpc.clone()).await;
+                loop {
+                    match feed.next_tip().await {
+                        Ok(tip) => {
+                            self.set_chain_blocknumber(&chain, network_type.clone(), tip, rpc)
+                                .await
+                        }
+                        Err(e) => {
+                            eprintln!(
+                                "Tip subscription dropped chain: {} rpc: {} {}",
+                                chain,
+                                rpc_host(ws),
+                                e
+                            );
+                            break;
+                        }
+                    }
+                }
+                self.subscribed.invalidate(&*chain).await;
+            }
+            time::sleep(RESUBSCRIBE_DELAY).await;
+        }
     }
-    pub async fn get_btc_block_number(&self, rpc: String) -> Result<u64, Box<dyn Error>> {
-        let endpoint = format!("{}blocks/tip/height", rpc);
-        let response = self.client.get(endpoint).send().await?;
-        let block_number = response.text().await.unwrap().parse()?;
-        println!("BTC block num - {:?}", block_number);
-        Ok(block_number)
+
+    /// Fetches the block number of `chain` and, when it succeeds, its finality and
+    /// tip header from the same rpc.
+    async fn refresh_chain(&self, chain: Arc<String>, network_type: NetworkType) {
+        if let Some((blocknumber, source_rpc)) = self.get_chain_blocknumber(chain.clone()).await {
+            self.set_chain_blocknumber(&chain, network_type.clone(), blocknumber, &source_rpc)
+                .await;
+            self.refresh_tip_details(&chain, network_type, &source_rpc)
+                .await;
+        }
     }
 
-    pub async fn fetch_ethereum_block_number(&self, rpc_url: &str) -> Result<u64, Box<dyn Error>> {
-        let payload = json!({
-            "jsonrpc": "2.0",
-            "id": 1,
-            "method": "eth_blockNumber",
-            "params": []
-        });
+    /// Fetches the finality, secondary heights and tip header of `chain` from `rpc`,
+    /// for the block number currently cached.
+    async fn refresh_tip_details(&self, chain: &str, network_type: NetworkType, rpc: &str) {
+        let Some(blocknumber) = self.cache(&network_type).get(chain).await else {
+            return;
+        };
+        self.update_finality(chain, rpc).await;
+        self.update_layer_heights(chain, rpc).await;
+        self.update_slot_heights(chain, rpc, blocknumber).await;
+        self.update_pending(chain, rpc).await;
+        self.update_header(chain, network_type, rpc, blocknumber)
+            .await;
+    }
+}
 
-        let res: serde_json::Value = self
-            .client
-            .post(rpc_url)
-            .json(&payload)
-            .send()
-            .await?
-            .json()
-            .await?;
+async fn timed_fetch_tip(adapter: &dyn ChainAdapter, rpc: &str) -> (AdapterResult<u64>, Duration) {
+    let started = Instant::now();
+    let result = adapter.fetch_tip(rpc).await;
+    (result, started.elapsed())
+}
+
+pub struct QuorumResolution<'a> {
+    pub blocknumber: Option<u64>,
+    /// Rpcs whose height is more than `tolerance` blocks away from the median.
+    pub divergent: Vec<(&'a str, u64)>,
+}
 
-        let hex_str = res["result"]
-            .as_str()
-            .ok_or("Invalid eth_blockNumber response")?;
+pub fn resolve_quorum<'a>(
+    reported: &[(&'a str, u64)],
+    quorum: &QuorumConfig,
+) -> QuorumResolution<'a> {
+    let mut heights: Vec<u64> = reported.iter().map(|(_, height)| *height).collect();
+    heights.sort_unstable();
+    // take the lower median so a single inflated rpc can't pull the height up
+    let median = match heights.get(heights.len().saturating_sub(1) / 2) {
+        Some(median) => *median,
+        None => {
+            return QuorumResolution {
+                blocknumber: None,
+                divergent: Vec::new(),
+            }
+        }
+    };
 
-        Ok(u64::from_str_radix(&hex_str.trim_start_matches("0x"), 16)?)
+    let (agreeing, divergent): (Vec<_>, Vec<_>) = reported
+        .iter()
+        .partition(|(_, height)| height.abs_diff(median) <= quorum.tolerance);
+
+    let blocknumber = if agreeing.len() < quorum.min_responses.max(1) {
+        None
+    } else {
+        match quorum.strategy {
+            QuorumStrategy::MEDIAN => Some(median),
+            QuorumStrategy::MAX => agreeing.iter().map(|(_, height)| *height).max(),
+        }
+    };
+
+    QuorumResolution {
+        blocknumber,
+        divergent,
     }
+}
 
-    pub async fn fetch_arbitrum_l1_block_number(
-        &self,
-        rpc_url: &str,
-    ) -> Result<u64, Box<dyn Error>> {
-        let payload = json!({
-            "jsonrpc": "2.0",
-            "id": 1,
-            "method": "eth_getBlockByNumber",
-            "params": ["latest", false]
-        });
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        let res: serde_json::Value = self
-            .client
-            .post(rpc_url)
-            .json(&payload)
-            .send()
-            .await?
-            .json()
-            .await?;
-
-        let l1_block_number = res["result"]["l1BlockNumber"]
-            .as_str()
-            .ok_or("Missing l1BlockNumber")?;
-
-        Ok(u64::from_str_radix(
-            &l1_block_number.trim_start_matches("0x"),
-            16,
-        )?)
+    fn quorum(strategy: QuorumStrategy, tolerance: u64, min_responses: usize) -> QuorumConfig {
+        QuorumConfig {
+            strategy,
+            tolerance,
+            min_responses,
+        }
     }
 
-    pub async fn fetch_solana_block_number(&self, rpc_url: &str) -> Result<u64, Box<dyn Error>> {
-        let payload = json!({
-            "jsonrpc": "2.0",
-            "id": 1,
-            "method": "getSlot",
-            "params": [{ "commitment": "confirmed" }]
-        });
+    #[test]
+    fn takes_the_median_of_an_odd_count() {
+        let reported = [("a", 102), ("b", 100), ("c", 101)];
+        let resolution = resolve_quorum(&reported, &quorum(QuorumStrategy::MEDIAN, 2, 1));
+        assert_eq!(resolution.blocknumber, Some(101));
+        assert!(resolution.divergent.is_empty());
+    }
 
-        let res: serde_json::Value = self
-            .client
-            .post(rpc_url)
-            .json(&payload)
-            .send()
-            .await?
-            .json()
-            .await?;
+    #[test]
+    fn takes_the_lower_median_of_an_even_count() {
+        let reported = [("a", 100), ("b", 103), ("c", 101), ("d", 102)];
+        let resolution = resolve_quorum(&reported, &quorum(QuorumStrategy::MEDIAN, 5, 1));
+        assert_eq!(resolution.blocknumber, Some(101));
+    }
 
-        Ok(res["result"].as_u64().ok_or("Invalid getSlot response")?)
+    #[test]
+    fn flags_outliers_beyond_tolerance() {
+        let reported = [("a", 100), ("b", 101), ("c", 5_000), ("d", 3)];
+        let resolution = resolve_quorum(&reported, &quorum(QuorumStrategy::MEDIAN, 2, 1));
+        assert_eq!(resolution.blocknumber, Some(100));
+        assert_eq!(resolution.divergent, vec![("c", 5_000), ("d", 3)]);
     }
 
-    pub async fn fetch_starknet_block_number(&self, rpc_url: &str) -> Result<u64, Box<dyn Error>> {
-        let payload = json!({
-            "jsonrpc": "2.0",
-            "id": 1,
-            "method": "starknet_blockNumber",
-            "params": []
-        });
+    #[test]
+    fn max_takes_the_highest_height_within_tolerance() {
+        let reported = [("a", 100), ("b", 102), ("c", 101), ("d", 110)];
+        let resolution = resolve_quorum(&reported, &quorum(QuorumStrategy::MAX, 2, 1));
+        assert_eq!(resolution.blocknumber, Some(102));
+        assert_eq!(resolution.divergent, vec![("d", 110)]);
+    }
 
-        let res: serde_json::Value = self
-            .client
-            .post(rpc_url)
-            .json(&payload)
-            .send()
-            .await?
-            .json()
-            .await?;
-
-        match &res["result"] {
-            serde_json::Value::Number(n) => Ok(n.as_u64().ok_or("Invalid number")?),
-            _ => Err("Unexpected starknet block number format".into()),
-        }
+    #[test]
+    fn needs_min_responses_agreeing_rpcs() {
+        let reported = [("a", 100), ("b", 200)];
+        let resolution = resolve_quorum(&reported, &quorum(QuorumStrategy::MEDIAN, 2, 2));
+        assert_eq!(resolution.blocknumber, None);
+        assert!(resolve_quorum(&[], &quorum(QuorumStrategy::MEDIAN, 2, 1))
+            .blocknumber
+            .is_none());
     }
 }
@@ -0,0 +1,295 @@
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    future::Future,
+    sync::Mutex,
+};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::{
+    chains::{AdapterResult, BlockHeader},
+    models::assets::NetworkType,
+};
+
+/// Number of recent tip headers kept per chain to compare against.
+const HEADER_WINDOW: usize = 64;
+/// Number of reorg events kept across all chains.
+const MAX_REORGS: usize = 256;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReorgEvent {
+    #[serde(rename = "networkType")]
+    pub network_type: NetworkType,
+    pub chain: String,
+    /// Number of previously seen blocks that are no longer canonical.
+    pub depth: u64,
+    /// Height of the first replaced block.
+    #[serde(rename = "forkHeight")]
+    pub fork_height: u64,
+    #[serde(rename = "oldHash")]
+    pub old_hash: String,
+    #[serde(rename = "newHash")]
+    pub new_hash: String,
+    #[serde(rename = "detectedAt")]
+    pub detected_at: DateTime<Utc>,
+}
+
+/// Recently seen tip headers of every chain along with the reorgs detected among them.
+#[derive(Default)]
+pub struct ReorgTracker {
+    headers: Mutex<HashMap<String, BTreeMap<u64, BlockHeader>>>,
+    reorgs: Mutex<VecDeque<ReorgEvent>>,
+}
+
+impl ReorgTracker {
+    /// Seen headers of `chain` at or below `height`, newest first.
+    pub fn headers_below(&self, chain: &str, height: u64) -> Vec<BlockHeader> {
+        let headers = self.headers.lock().unwrap();
+        headers
+            .get(chain)
+            .map(|headers| {
+                headers
+                    .range(..=height)
+                    .rev()
+                    .map(|(_, h)| h.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Highest height seen for `chain`.
+    pub fn highest_height(&self, chain: &str) -> Option<u64> {
+        let headers = self.headers.lock().unwrap();
+        headers
+            .get(chain)
+            .and_then(|headers| headers.last_key_value())
+            .map(|(height, _)| *height)
+    }
+
+    pub fn header(&self, chain: &str, height: u64) -> Option<BlockHeader> {
+        let headers = self.headers.lock().unwrap();
+        headers
+            .get(chain)
+            .and_then(|headers| headers.get(&height))
+            .cloned()
+    }
+
+    /// Compares `tip` with the headers seen on earlier ticks and remembers it as
+    /// canonical. Seen headers are walked back from the tip, fetching the canonical
+    /// header at each height with `fetch`, until one is still canonical. A reorg is
+    /// recorded from the deepest replaced header; when every retained header was
+    /// replaced, the fork is at least that deep. Nothing is recorded if a fetch fails.
+    pub async fn observe<F, Fut>(
+        &self,
+        chain: &str,
+        network_type: NetworkType,
+        tip: BlockHeader,
+        fetch: F,
+    ) -> AdapterResult<Option<ReorgEvent>>
+    where
+        F: Fn(u64) -> Fut,
+        Fut: Future<Output = AdapterResult<BlockHeader>>,
+    {
+        let mut child = tip.clone();
+        let mut replaced: Option<(BlockHeader, BlockHeader)> = None;
+        for seen in self.headers_below(chain, tip.number) {
+            let canonical = if seen.number == tip.number {
+                tip.clone()
+            } else if seen.number + 1 == child.number && child.parent_hash == seen.hash {
+                break;
+            } else {
+                fetch(seen.number).await?
+            };
+            if canonical.hash == seen.hash {
+                break;
+            }
+            child = canonical.clone();
+            replaced = Some((seen, canonical));
+        }
+
+        let Some((old, new)) = replaced else {
+            self.record_header(chain, tip, None);
+            return Ok(None);
+        };
+        let highest = self.highest_height(chain).unwrap_or(old.number);
+        let event = ReorgEvent {
+            network_type,
+            chain: chain.to_string(),
+            depth: highest - old.number + 1,
+            fork_height: old.number,
+            old_hash: old.hash,
+            new_hash: new.hash,
+            detected_at: Utc::now(),
+        };
+        self.record_reorg(event.clone());
+        self.record_header(chain, tip, Some(new.number));
+        Ok(Some(event))
+    }
+
+    /// Remembers `header` as canonical. Seen headers from `fork_height` upwards are
+    /// dropped first when a reorg replaced them.
+    pub fn record_header(&self, chain: &str, header: BlockHeader, fork_height: Option<u64>) {
+        let mut headers = self.headers.lock().unwrap();
+        let headers = headers.entry(chain.to_string()).or_default();
+        if let Some(fork_height) = fork_height {
+            headers.split_off(&fork_height);
+        }
+        headers.insert(header.number, header);
+        while headers.len() > HEADER_WINDOW {
+            headers.pop_first();
+        }
+    }
+
+    pub fn record_reorg(&self, event: ReorgEvent) {
+        let mut reorgs = self.reorgs.lock().unwrap();
+        reorgs.push_front(event);
+        reorgs.truncate(MAX_REORGS);
+    }
+
+    /// Detected reorgs, newest first.
+    pub fn reorgs(&self) -> Vec<ReorgEvent> {
+        self.reorgs.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    const CHAIN: &str = "ethereum_sepolia";
+
+    /// Header `number` of the fork named `branch`, which splits off the `a` branch at
+    /// `fork`.
+    fn header(branch: char, fork: u64, number: u64) -> BlockHeader {
+        let name = |number: u64| {
+            let branch = if number >= fork { branch } else { 'a' };
+            format!("{}{}", branch, number)
+        };
+        BlockHeader {
+            number,
+            hash: name(number),
+            parent_hash: name(number.saturating_sub(1)),
+            timestamp: number * 12,
+        }
+    }
+
+    /// Observes `tip` against the `b` branch forked at `fork`, returning the reorg and
+    /// the number of headers that had to be fetched.
+    async fn observe(
+        tracker: &ReorgTracker,
+        tip: BlockHeader,
+        fork: u64,
+    ) -> (Option<ReorgEvent>, u32) {
+        let fetches = AtomicU32::new(0);
+        let fetch = |number| {
+            fetches.fetch_add(1, Ordering::SeqCst);
+            async move { Ok(header('b', fork, number)) }
+        };
+        let event = tracker
+            .observe(CHAIN, NetworkType::TESTNET, tip, fetch)
+            .await
+            .unwrap();
+        (event, fetches.load(Ordering::SeqCst))
+    }
+
+    async fn seen(tracker: &ReorgTracker, numbers: std::ops::RangeInclusive<u64>) {
+        for number in numbers {
+            let (event, _) = observe(tracker, header('a', u64::MAX, number), u64::MAX).await;
+            assert!(event.is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn extends_without_fetching() {
+        let tracker = ReorgTracker::default();
+        seen(&tracker, 100..=101).await;
+        let (event, fetches) = observe(&tracker, header('a', u64::MAX, 102), u64::MAX).await;
+        assert!(event.is_none());
+        assert_eq!(fetches, 0);
+        assert_eq!(tracker.highest_height(CHAIN), Some(102));
+        assert_eq!(tracker.headers_below(CHAIN, u64::MAX).len(), 3);
+        assert!(tracker.reorgs().is_empty());
+    }
+
+    #[tokio::test]
+    async fn detects_a_one_block_reorg() {
+        let tracker = ReorgTracker::default();
+        seen(&tracker, 100..=101).await;
+        let (event, fetches) = observe(&tracker, header('b', 101, 101), 101).await;
+        let event = event.unwrap();
+        assert_eq!(fetches, 0);
+        assert_eq!(event.depth, 1);
+        assert_eq!(event.fork_height, 101);
+        assert_eq!(event.old_hash, "a101");
+        assert_eq!(event.new_hash, "b101");
+        assert_eq!(tracker.header(CHAIN, 101).unwrap().hash, "b101");
+        assert_eq!(tracker.header(CHAIN, 100).unwrap().hash, "a100");
+        assert_eq!(tracker.reorgs().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn walks_back_a_deep_reorg() {
+        let tracker = ReorgTracker::default();
+        seen(&tracker, 100..=105).await;
+        let (event, fetches) = observe(&tracker, header('b', 103, 106), 103).await;
+        let event = event.unwrap();
+        // 105, 104 and 103 were replaced; 102 is the tip's ancestor by hash
+        assert_eq!(fetches, 3);
+        assert_eq!(event.depth, 3);
+        assert_eq!(event.fork_height, 103);
+        assert_eq!(event.old_hash, "a103");
+        assert_eq!(event.new_hash, "b103");
+        // the replaced headers are dropped, the common ones kept
+        let heights: Vec<u64> = tracker
+            .headers_below(CHAIN, u64::MAX)
+            .iter()
+            .map(|header| header.number)
+            .collect();
+        assert_eq!(heights, [106, 102, 101, 100]);
+    }
+
+    #[tokio::test]
+    async fn counts_replaced_headers_above_a_lower_tip() {
+        let tracker = ReorgTracker::default();
+        seen(&tracker, 100..=105).await;
+        // the new branch is shorter than the one it replaced
+        let (event, _) = observe(&tracker, header('b', 103, 104), 103).await;
+        let event = event.unwrap();
+        assert_eq!(event.depth, 3);
+        assert_eq!(event.fork_height, 103);
+        assert_eq!(tracker.highest_height(CHAIN), Some(104));
+    }
+
+    #[tokio::test]
+    async fn bounds_forks_deeper_than_the_retained_headers() {
+        let tracker = ReorgTracker::default();
+        let window = HEADER_WINDOW as u64;
+        seen(&tracker, 1..=window + 10).await;
+        assert_eq!(tracker.headers_below(CHAIN, u64::MAX).len(), HEADER_WINDOW);
+
+        let (event, fetches) = observe(&tracker, header('b', 0, window + 11), 0).await;
+        let event = event.unwrap();
+        // every retained header was replaced, so the fork is at least the window deep
+        assert_eq!(fetches, window as u32);
+        assert_eq!(event.depth, window);
+        assert_eq!(event.fork_height, 11);
+        assert_eq!(tracker.headers_below(CHAIN, u64::MAX).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn records_nothing_when_a_fetch_fails() {
+        let tracker = ReorgTracker::default();
+        seen(&tracker, 100..=101).await;
+        let fetch = |_| async { Err("rpc down".into()) };
+        let tip = header('b', 101, 102);
+        assert!(tracker
+            .observe(CHAIN, NetworkType::TESTNET, tip, fetch)
+            .await
+            .is_err());
+        assert_eq!(tracker.highest_height(CHAIN), Some(101));
+        assert!(tracker.reorgs().is_empty());
+    }
+}
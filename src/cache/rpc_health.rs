@@ -0,0 +1,312 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::utils::rpc_host;
+
+/// Consecutive failures after which an rpc's circuit opens.
+const FAILURE_THRESHOLD: u32 = 3;
+const BASE_BACKOFF: Duration = Duration::from_secs(10);
+const MAX_BACKOFF: Duration = Duration::from_secs(600);
+/// Weight of the newest sample in the latency moving average.
+const LATENCY_ALPHA: f64 = 0.2;
+
+#[derive(Debug, Default, Clone)]
+pub struct RpcHealth {
+    pub successes: u64,
+    pub failures: u64,
+    pub consecutive_failures: u32,
+    /// Answers that were too far from the quorum's height to be used.
+    pub divergences: u64,
+    pub latency_ewma_ms: Option<f64>,
+    pub open_until: Option<Instant>,
+    pub last_error: Option<String>,
+}
+
+impl RpcHealth {
+    /// Success rate smoothed towards 50% so a single early sample doesn't dominate.
+    pub fn success_rate(&self) -> f64 {
+        (self.successes as f64 + 1.0) / ((self.successes + self.failures) as f64 + 2.0)
+    }
+
+    /// Higher is better: reliable rpcs first, then faster ones.
+    pub fn score(&self) -> f64 {
+        self.success_rate() / (1.0 + self.latency_ewma_ms.unwrap_or(0.0) / 1000.0)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.open_until.is_none()
+    }
+
+    /// An open circuit whose backoff has elapsed, due for a health probe.
+    pub fn is_half_open(&self, now: Instant) -> bool {
+        self.open_until.is_some_and(|open_until| now >= open_until)
+    }
+
+    fn record_success(&mut self, latency: Duration) {
+        let latency_ms = latency.as_secs_f64() * 1000.0;
+        self.latency_ewma_ms = Some(match self.latency_ewma_ms {
+            Some(ewma) => LATENCY_ALPHA * latency_ms + (1.0 - LATENCY_ALPHA) * ewma,
+            None => latency_ms,
+        });
+        self.successes += 1;
+        self.consecutive_failures = 0;
+        self.open_until = None;
+    }
+
+    fn record_failure(&mut self, error: String, now: Instant) {
+        self.failures += 1;
+        self.consecutive_failures += 1;
+        self.last_error = Some(error);
+        if self.consecutive_failures >= FAILURE_THRESHOLD {
+            let exponent = (self.consecutive_failures - FAILURE_THRESHOLD).min(16);
+            let backoff = BASE_BACKOFF.saturating_mul(1 << exponent).min(MAX_BACKOFF);
+            self.open_until = Some(now + backoff);
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RpcHealthReport {
+    /// Position of the rpc in the network's `rpcs` config.
+    pub index: usize,
+    pub host: String,
+    pub score: f64,
+    #[serde(rename = "successRate")]
+    pub success_rate: f64,
+    #[serde(rename = "latencyMs")]
+    pub latency_ms: Option<f64>,
+    pub successes: u64,
+    pub failures: u64,
+    #[serde(rename = "consecutiveFailures")]
+    pub consecutive_failures: u32,
+    pub divergences: u64,
+    /// `closed`, `open` or `half-open`.
+    pub circuit: &'static str,
+    #[serde(rename = "retryInSecs", skip_serializing_if = "Option::is_none")]
+    pub retry_in_secs: Option<u64>,
+    #[serde(rename = "lastError", skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// Health of every rpc, keyed by chain and then by rpc url.
+#[derive(Default)]
+pub struct RpcHealthTracker {
+    states: Mutex<HashMap<String, HashMap<String, RpcHealth>>>,
+}
+
+impl RpcHealthTracker {
+    fn health(&self, chain: &str, rpc: &str) -> RpcHealth {
+        let states = self.states.lock().unwrap();
+        states
+            .get(chain)
+            .and_then(|chain_states| chain_states.get(rpc))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Returns the rpcs whose circuit is closed, best score first. Ties keep the
+    /// configured order.
+    pub fn ordered_rpcs(&self, chain: &str, rpcs: &[String]) -> Vec<String> {
+        let mut available: Vec<(f64, &String)> = rpcs
+            .iter()
+            .map(|rpc| (self.health(chain, rpc), rpc))
+            .filter(|(health, _)| health.is_closed())
+            .map(|(health, rpc)| (health.score(), rpc))
+            .collect();
+        available.sort_by(|a, b| b.0.total_cmp(&a.0));
+        available.into_iter().map(|(_, rpc)| rpc.clone()).collect()
+    }
+
+    /// Returns the rpcs whose circuit is half-open, to be probed before they serve
+    /// requests again.
+    pub fn half_open_rpcs(&self, chain: &str, rpcs: &[String]) -> Vec<String> {
+        let now = Instant::now();
+        rpcs.iter()
+            .filter(|rpc| self.health(chain, rpc).is_half_open(now))
+            .cloned()
+            .collect()
+    }
+
+    pub fn record_success(&self, chain: &str, rpc: &str, latency: Duration) {
+        let mut states = self.states.lock().unwrap();
+        states
+            .entry(chain.to_string())
+            .or_default()
+            .entry(rpc.to_string())
+            .or_default()
+            .record_success(latency);
+    }
+
+    pub fn record_failure(&self, chain: &str, rpc: &str, error: String) {
+        let mut states = self.states.lock().unwrap();
+        states
+            .entry(chain.to_string())
+            .or_default()
+            .entry(rpc.to_string())
+            .or_default()
+            .record_failure(error, Instant::now());
+    }
+
+    pub fn record_divergence(&self, chain: &str, rpc: &str) {
+        let mut states = self.states.lock().unwrap();
+        states
+            .entry(chain.to_string())
+            .or_default()
+            .entry(rpc.to_string())
+            .or_default()
+            .divergences += 1;
+    }
+
+    pub fn report(&self, chain: &str, rpcs: &[String]) -> Vec<RpcHealthReport> {
+        let now = Instant::now();
+        rpcs.iter()
+            .enumerate()
+            .map(|(index, rpc)| {
+                let health = self.health(chain, rpc);
+                let circuit = match health.open_until {
+                    None => "closed",
+                    Some(open_until) if now < open_until => "open",
+                    Some(_) => "half-open",
+                };
+                RpcHealthReport {
+                    index,
+                    host: rpc_host(rpc),
+                    score: health.score(),
+                    success_rate: health.success_rate(),
+                    latency_ms: health.latency_ewma_ms,
+                    successes: health.successes,
+                    failures: health.failures,
+                    consecutive_failures: health.consecutive_failures,
+                    divergences: health.divergences,
+                    circuit,
+                    retry_in_secs: health
+                        .open_until
+                        .filter(|open_until| now < *open_until)
+                        .map(|open_until| (open_until - now).as_secs()),
+                    last_error: health.last_error,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHAIN: &str = "ethereum_sepolia";
+
+    fn rpcs() -> Vec<String> {
+        [
+            "https://a.example.com",
+            "https://b.example.com",
+            "https://c.example.com",
+        ]
+        .map(String::from)
+        .to_vec()
+    }
+
+    #[test]
+    fn orders_by_success_rate_then_latency() {
+        let tracker = RpcHealthTracker::default();
+        let rpcs = rpcs();
+        // untracked rpcs keep the configured order
+        assert_eq!(tracker.ordered_rpcs(CHAIN, &rpcs), rpcs);
+
+        tracker.record_success(CHAIN, &rpcs[0], Duration::from_millis(400));
+        tracker.record_success(CHAIN, &rpcs[1], Duration::from_millis(50));
+        tracker.record_success(CHAIN, &rpcs[2], Duration::from_millis(50));
+        tracker.record_failure(CHAIN, &rpcs[2], "timed out".to_string());
+        assert_eq!(
+            tracker.ordered_rpcs(CHAIN, &rpcs),
+            [&rpcs[1], &rpcs[0], &rpcs[2]].map(String::clone)
+        );
+    }
+
+    #[test]
+    fn smooths_latency() {
+        let mut health = RpcHealth::default();
+        health.record_success(Duration::from_millis(100));
+        assert_eq!(health.latency_ewma_ms, Some(100.0));
+        health.record_success(Duration::from_millis(600));
+        assert_eq!(health.latency_ewma_ms, Some(200.0));
+    }
+
+    #[test]
+    fn opens_the_circuit_after_consecutive_failures() {
+        let tracker = RpcHealthTracker::default();
+        let rpcs = rpcs();
+        for _ in 0..FAILURE_THRESHOLD - 1 {
+            tracker.record_failure(CHAIN, &rpcs[0], "timed out".to_string());
+        }
+        assert!(tracker.ordered_rpcs(CHAIN, &rpcs).contains(&rpcs[0]));
+
+        tracker.record_failure(CHAIN, &rpcs[0], "timed out".to_string());
+        assert_eq!(tracker.ordered_rpcs(CHAIN, &rpcs), rpcs[1..]);
+        assert!(tracker.half_open_rpcs(CHAIN, &rpcs).is_empty());
+        let report = tracker.report(CHAIN, &rpcs);
+        assert_eq!(report[0].circuit, "open");
+        assert!(report[0]
+            .retry_in_secs
+            .is_some_and(|secs| secs <= BASE_BACKOFF.as_secs()));
+        assert_eq!(report[0].last_error.as_deref(), Some("timed out"));
+        assert_eq!(report[1].circuit, "closed");
+    }
+
+    #[test]
+    fn doubles_the_backoff_up_to_the_cap() {
+        let now = Instant::now();
+        let mut health = RpcHealth::default();
+        let mut backoffs = Vec::new();
+        for _ in 0..12 {
+            health.record_failure("timed out".to_string(), now);
+            backoffs.push(health.open_until.map(|open_until| open_until - now));
+        }
+        let secs = |secs| Some(Duration::from_secs(secs));
+        assert_eq!(
+            backoffs[..8],
+            [
+                None,
+                None,
+                secs(10),
+                secs(20),
+                secs(40),
+                secs(80),
+                secs(160),
+                secs(320)
+            ]
+        );
+        assert!(backoffs[8..]
+            .iter()
+            .all(|backoff| *backoff == Some(MAX_BACKOFF)));
+    }
+
+    #[test]
+    fn recovers_through_half_open() {
+        let now = Instant::now();
+        let mut health = RpcHealth::default();
+        for _ in 0..FAILURE_THRESHOLD {
+            health.record_failure("timed out".to_string(), now);
+        }
+        assert!(!health.is_closed());
+        assert!(!health.is_half_open(now));
+        assert!(health.is_half_open(now + BASE_BACKOFF));
+
+        // a failed probe opens it for longer
+        health.record_failure("timed out".to_string(), now + BASE_BACKOFF);
+        assert!(!health.is_half_open(now + BASE_BACKOFF * 2));
+        assert!(health.is_half_open(now + BASE_BACKOFF * 3));
+
+        // a successful one closes it and resets the failure streak
+        health.record_success(Duration::from_millis(50));
+        assert!(health.is_closed());
+        assert_eq!(health.consecutive_failures, 0);
+        health.record_failure("timed out".to_string(), now);
+        assert!(health.is_closed());
+    }
+}
@@ -0,0 +1,410 @@
+use std::{error::Error, fmt, sync::Arc};
+
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use self::http::{HttpClient, RpcRequest};
+use crate::models::{
+    assets::{ChainFamily, Network},
+    balances::{BalanceQuery, UtxoAddressBalance},
+};
+
+pub mod aptos;
+pub mod arbitrum;
+pub mod bitcoin;
+pub mod cosmos;
+pub mod evm;
+pub mod http;
+#[cfg(test)]
+pub mod mock_server;
+pub mod opstack;
+pub mod scroll;
+pub mod solana;
+pub mod starknet;
+pub mod subscriptions;
+pub mod sui;
+pub mod ton;
+pub mod tron;
+pub mod zksync;
+
+pub type AdapterResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+
+/// JSON-RPC error code for a method the rpc doesn't implement.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// How final a block height is. Not every family distinguishes all three; `SAFE`
+/// falls back to the closest notion the chain has.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BlockTag {
+    LATEST,
+    SAFE,
+    FINALIZED,
+}
+
+/// The identity of a block, used to notice when a height we already saw gets replaced.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BlockHeader {
+    pub number: u64,
+    pub hash: String,
+    #[serde(rename = "parentHash")]
+    pub parent_hash: String,
+    /// Unix timestamp in seconds.
+    pub timestamp: u64,
+}
+
+/// Both heights of an L2 chain: its own block number and the L1 height it is
+/// anchored to.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct LayerHeights {
+    #[serde(rename = "l1BlockNumber", skip_serializing_if = "Option::is_none")]
+    pub l1: Option<u64>,
+    /// Latest batch committed to L1, for rollups that anchor batches rather than
+    /// tracking L1 blocks.
+    #[serde(rename = "l1BatchNumber", skip_serializing_if = "Option::is_none")]
+    pub l1_batch: Option<u64>,
+    #[serde(rename = "l2BlockNumber")]
+    pub l2: u64,
+}
+
+/// Both heights of a solana chain: its slot and the number of blocks produced, which
+/// falls behind the slot whenever a leader skips its slot.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct SlotHeights {
+    pub slot: u64,
+    #[serde(rename = "blockHeight")]
+    pub block_height: u64,
+}
+
+/// How to follow a chain's tip over a websocket: the JSON-RPC subscription to open
+/// and how to read the tip height from each of its notifications.
+#[derive(Debug, Clone)]
+pub struct TipSubscription {
+    pub method: &'static str,
+    pub params: Value,
+    /// Reads the tip height from a notification's `params.result`.
+    pub parse: fn(&Value) -> AdapterResult<u64>,
+}
+
+/// Fetches block heights for one chain family. Each configured chain gets its own
+/// adapter instance, picked from the `chainFamily` field of its network config.
+#[async_trait]
+pub trait ChainAdapter: Send + Sync {
+    /// Returns the current tip height reported by `rpc`.
+    async fn fetch_tip(&self, rpc: &str) -> AdapterResult<u64>;
+
+    /// Returns the height of the block identified by `tag`.
+    async fn fetch_by_tag(&self, rpc: &str, tag: BlockTag) -> AdapterResult<u64>;
+
+    /// Checks whether `rpc` is reachable and answering.
+    async fn health_probe(&self, rpc: &str) -> AdapterResult<()> {
+        self.fetch_tip(rpc).await.map(|_| ())
+    }
+
+    /// Whether this family is an L2 that can report both of its heights through
+    /// `fetch_layer_heights`.
+    fn is_layer2(&self) -> bool {
+        false
+    }
+
+    /// Returns the latest L2 height together with the L1 height it is anchored to.
+    async fn fetch_layer_heights(&self, _rpc: &str) -> AdapterResult<LayerHeights> {
+        Err("Layer heights are only available on L2 chain families".into())
+    }
+
+    /// Whether this family has slots besides block heights and can report both
+    /// through `fetch_slot_heights`.
+    fn supports_slot_heights(&self) -> bool {
+        false
+    }
+
+    /// Returns the latest slot together with the latest block height. `tip` is the
+    /// height last published for the chain, so only the other one is fetched.
+    async fn fetch_slot_heights(&self, _rpc: &str, _tip: u64) -> AdapterResult<SlotHeights> {
+        Err("Slot heights are only available on slot-based chain families".into())
+    }
+
+    /// Whether this family exposes the block currently being built through
+    /// `fetch_pending`.
+    fn supports_pending(&self) -> bool {
+        false
+    }
+
+    /// Returns the number of the pending block, one above the latest accepted block
+    /// once the sequencer has started building on it.
+    async fn fetch_pending(&self, _rpc: &str) -> AdapterResult<u64> {
+        Err("Pending blocks are not supported for this chain family".into())
+    }
+
+    /// The websocket subscription that pushes the same height as `fetch_tip`, if the
+    /// family has one.
+    fn tip_subscription(&self) -> Option<TipSubscription> {
+        None
+    }
+
+    /// Whether this family can return block headers through `fetch_header`.
+    fn supports_headers(&self) -> bool {
+        false
+    }
+
+    /// Returns the header of the block at `height`, as published by `fetch_tip`.
+    async fn fetch_header(&self, _rpc: &str, _height: u64) -> AdapterResult<BlockHeader> {
+        Err("Block headers are not supported for this chain family".into())
+    }
+
+    /// Whether this family can report address balances through `fetch_balances`.
+    fn supports_balances(&self) -> bool {
+        false
+    }
+
+    /// Returns one balance per query, in the same order as `queries`, or `None` for a
+    /// balance too large to fit in a `u128`.
+    async fn fetch_balances(
+        &self,
+        _rpc: &str,
+        _queries: &[BalanceQuery],
+    ) -> AdapterResult<Vec<Option<u128>>> {
+        Err("Balances are not supported for this chain family".into())
+    }
+
+    /// Whether this family tracks unspent outputs and can serve `fetch_utxo_balance`.
+    fn supports_utxos(&self) -> bool {
+        false
+    }
+
+    /// Returns the spendable and pending balance of `address` along with its UTXO set.
+    async fn fetch_utxo_balance(
+        &self,
+        _rpc: &str,
+        _address: &str,
+    ) -> AdapterResult<UtxoAddressBalance> {
+        Err("UTXOs are not supported for this chain family".into())
+    }
+}
+
+impl ChainFamily {
+    /// Fails when the network's config can't be applied to its rpcs.
+    pub fn adapter(
+        &self,
+        client: HttpClient,
+        network: &Network,
+    ) -> AdapterResult<Arc<dyn ChainAdapter>> {
+        Ok(match self {
+            ChainFamily::EVM => Arc::new(evm::EvmAdapter::new(client)),
+            ChainFamily::ARBITRUM => Arc::new(arbitrum::ArbitrumAdapter::new(
+                client,
+                &network.network_type,
+                network.primary_height,
+            )),
+            ChainFamily::BITCOIN
+            | ChainFamily::LITECOIN
+            | ChainFamily::DOGECOIN
+            | ChainFamily::BITCOINCASH => Arc::new(bitcoin::BitcoinAdapter::new(
+                client,
+                network.finality_confirmations,
+                &network.http,
+                &network.rpcs,
+            )?),
+            ChainFamily::OPSTACK => {
+                Arc::new(opstack::OpStackAdapter::new(client, network.primary_height))
+            }
+            ChainFamily::ZKSYNC => Arc::new(zksync::ZkSyncAdapter::new(client)),
+            ChainFamily::SCROLL => {
+                Arc::new(scroll::ScrollAdapter::new(client, network.primary_height))
+            }
+            ChainFamily::SUI => Arc::new(sui::SuiAdapter::new(client)),
+            ChainFamily::APTOS => Arc::new(aptos::AptosAdapter::new(client)),
+            ChainFamily::TRON => Arc::new(tron::TronAdapter::new(client)),
+            ChainFamily::TON => Arc::new(ton::TonAdapter::new(client)),
+            ChainFamily::COSMOS => Arc::new(cosmos::CosmosAdapter::new(client)),
+            ChainFamily::SOLANA => Arc::new(solana::SolanaAdapter::new(
+                client,
+                network.solana_height,
+                network.solana_commitment,
+            )),
+            ChainFamily::STARKNET => Arc::new(starknet::StarknetAdapter::new(client)),
+        })
+    }
+}
+
+/// Sends `request` and decodes its JSON body. An error status fails the request,
+/// unless its body holds a JSON-RPC `error`, which is decoded for the caller to
+/// report. The url is stripped from errors since rpc urls may embed api keys.
+pub async fn send_json<T: DeserializeOwned>(request: RpcRequest) -> AdapterResult<T> {
+    let response = request.send().await.map_err(reqwest::Error::without_url)?;
+    let Err(status_error) = response.error_for_status_ref().map(|_| ()) else {
+        return Ok(response.json().await.map_err(reqwest::Error::without_url)?);
+    };
+    let status_error = status_error.without_url();
+    let body = response
+        .bytes()
+        .await
+        .map_err(reqwest::Error::without_url)?;
+    match serde_json::from_slice::<Value>(&body) {
+        Ok(body) if has_json_rpc_error(&body) => {
+            serde_json::from_value(body).map_err(|_| status_error.into())
+        }
+        _ => Err(status_error.into()),
+    }
+}
+
+/// Whether `body` is a JSON-RPC response, or batch of them, carrying an `error`.
+fn has_json_rpc_error(body: &Value) -> bool {
+    match body {
+        Value::Array(responses) => responses.iter().any(has_json_rpc_error),
+        body => body.get("error").is_some_and(|error| !error.is_null()),
+    }
+}
+
+/// Like `send_json`, for endpoints that answer with plain text.
+pub async fn send_text(request: RpcRequest) -> AdapterResult<String> {
+    let response = request
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(reqwest::Error::without_url)?;
+    Ok(response.text().await.map_err(reqwest::Error::without_url)?)
+}
+
+/// An `error` object answered by a JSON-RPC server.
+#[derive(Debug)]
+pub struct JsonRpcError {
+    method: String,
+    error: Value,
+}
+
+impl JsonRpcError {
+    pub(crate) fn new(method: &str, error: Value) -> Self {
+        JsonRpcError {
+            method: method.to_string(),
+            error,
+        }
+    }
+
+    /// Whether `error` is a JSON-RPC error saying the method doesn't exist on the rpc.
+    pub fn is_method_not_found(error: &(dyn Error + 'static)) -> bool {
+        error
+            .downcast_ref::<JsonRpcError>()
+            .is_some_and(|e| e.error["code"].as_i64() == Some(METHOD_NOT_FOUND))
+    }
+}
+
+impl fmt::Display for JsonRpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} failed: {}", self.method, self.error)
+    }
+}
+
+impl Error for JsonRpcError {}
+
+/// Sends a JSON-RPC 2.0 request and returns its `result` field.
+pub async fn json_rpc(
+    client: &HttpClient,
+    rpc_url: &str,
+    method: &str,
+    params: Value,
+) -> AdapterResult<Value> {
+    let payload = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": params
+    });
+
+    let mut res: Value = send_json(client.post(rpc_url).json(&payload)).await?;
+
+    if let Some(error) = res.get_mut("error") {
+        return Err(JsonRpcError::new(method, error.take()).into());
+    }
+
+    Ok(res["result"].take())
+}
+
+/// Sends every `(method, params)` call in JSON-RPC 2.0 batch requests of at most
+/// the client's `maxBatchSize` calls, one after the other, and returns their
+/// `result` fields in the order the calls were given.
+pub async fn json_rpc_batch(
+    client: &HttpClient,
+    rpc_url: &str,
+    calls: &[(&str, Value)],
+) -> AdapterResult<Vec<Value>> {
+    let mut results = Vec::with_capacity(calls.len());
+    for chunk in calls.chunks(client.max_batch_size()) {
+        results.extend(send_batch(client, rpc_url, chunk).await?);
+    }
+    Ok(results)
+}
+
+async fn send_batch(
+    client: &HttpClient,
+    rpc_url: &str,
+    calls: &[(&str, Value)],
+) -> AdapterResult<Vec<Value>> {
+    let payload: Vec<Value> = calls
+        .iter()
+        .enumerate()
+        .map(|(id, (method, params))| {
+            json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "method": method,
+                "params": params
+            })
+        })
+        .collect();
+
+    let responses: Vec<Value> = send_json(client.post(rpc_url).json(&payload)).await?;
+
+    // batch responses may come back in any order, so match them up by id
+    let mut results = vec![None; calls.len()];
+    for mut response in responses {
+        let id = response["id"]
+            .as_u64()
+            .map(|id| id as usize)
+            .filter(|id| *id < calls.len())
+            .ok_or("Invalid id in batch response")?;
+        if let Some(error) = response.get_mut("error") {
+            return Err(JsonRpcError::new(calls[id].0, error.take()).into());
+        }
+        results[id] = Some(response["result"].take());
+    }
+
+    results
+        .into_iter()
+        .map(|result| result.ok_or_else(|| "Missing response in batch".into()))
+        .collect()
+}
+
+/// Appends `path` to a REST base url, keeping any query string (such as an api key).
+pub fn rest_url(base: &str, path: &str) -> AdapterResult<reqwest::Url> {
+    let mut url = reqwest::Url::parse(base)?;
+    let joined = format!("{}/{}", url.path().trim_end_matches('/'), path);
+    url.set_path(&joined);
+    Ok(url)
+}
+
+/// Parses a height given either as a JSON number or as a decimal string, as many
+/// apis encode 64-bit integers as strings.
+pub fn parse_u64(value: &Value) -> AdapterResult<u64> {
+    match value {
+        Value::Number(n) => Ok(n.as_u64().ok_or("Invalid number")?),
+        Value::String(s) => Ok(s.parse()?),
+        _ => Err("Expected a number or a decimal string".into()),
+    }
+}
+
+pub fn parse_hex_u64(value: &Value) -> AdapterResult<u64> {
+    let hex_str = value.as_str().ok_or("Expected a hex string")?;
+    Ok(u64::from_str_radix(hex_str.trim_start_matches("0x"), 16)?)
+}
+
+pub fn parse_hex_u128(value: &Value) -> AdapterResult<u128> {
+    let hex_str = value.as_str().ok_or("Expected a hex string")?;
+    let digits = hex_str.trim_start_matches("0x").trim_start_matches('0');
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    Ok(u128::from_str_radix(digits, 16)?)
+}
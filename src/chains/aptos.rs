@@ -0,0 +1,67 @@
+use async_trait::async_trait;
+use serde_json::Value;
+
+use super::{http::HttpClient, parse_u64, send_json, AdapterResult, BlockTag, ChainAdapter};
+
+/// Aptos reports the `block_height` of its REST ledger info, served at the api's base
+/// url (e.g. `https://fullnode.mainnet.aptoslabs.com/v1`). Blocks are final once
+/// committed, so every tag resolves to the same height.
+pub struct AptosAdapter {
+    client: HttpClient,
+}
+
+impl AptosAdapter {
+    pub fn new(client: HttpClient) -> Self {
+        AptosAdapter { client }
+    }
+}
+
+#[async_trait]
+impl ChainAdapter for AptosAdapter {
+    async fn fetch_tip(&self, rpc: &str) -> AdapterResult<u64> {
+        let ledger_info: Value = send_json(self.client.get(rpc)).await?;
+        parse_u64(&ledger_info["block_height"])
+    }
+
+    async fn fetch_by_tag(&self, rpc: &str, _tag: BlockTag) -> AdapterResult<u64> {
+        self.fetch_tip(rpc).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{http::StatusCode, routing::get, Json, Router};
+    use serde_json::json;
+
+    use super::*;
+    use crate::chains::mock_server::serve;
+
+    #[tokio::test]
+    async fn fetches_ledger_block_height() {
+        let base = serve(Router::new().route(
+            "/v1",
+            get(|| async {
+                Json(json!({
+                    "chain_id": 1,
+                    "epoch": "9021",
+                    "ledger_version": "2563094271",
+                    "block_height": "311042901",
+                    "node_role": "full_node"
+                }))
+            }),
+        ))
+        .await;
+        let adapter = AptosAdapter::new(HttpClient::default());
+        let rpc = format!("{}/v1", base);
+        assert_eq!(adapter.fetch_tip(&rpc).await.unwrap(), 311042901);
+    }
+
+    #[tokio::test]
+    async fn fails_on_error_status() {
+        let base =
+            serve(Router::new().route("/v1", get(|| async { StatusCode::SERVICE_UNAVAILABLE })))
+                .await;
+        let adapter = AptosAdapter::new(HttpClient::default());
+        assert!(adapter.fetch_tip(&format!("{}/v1", base)).await.is_err());
+    }
+}
@@ -0,0 +1,107 @@
+use async_trait::async_trait;
+use serde_json::json;
+
+use super::{
+    evm::{evm_block_tag, fetch_evm_balances, fetch_evm_header, new_heads_subscription},
+    http::HttpClient,
+    json_rpc, parse_hex_u64, AdapterResult, BlockHeader, BlockTag, ChainAdapter, LayerHeights,
+    TipSubscription,
+};
+use crate::models::{
+    assets::{HeightKind, NetworkType},
+    balances::BalanceQuery,
+};
+
+/// Arbitrum chains publish the L1 block number seen by the sequencer by default,
+/// since that is what `block.number` returns inside Arbitrum contracts. Localnet
+/// nodes are plain EVM devnets and report their own height instead.
+pub struct ArbitrumAdapter {
+    client: HttpClient,
+    primary: HeightKind,
+}
+
+impl ArbitrumAdapter {
+    pub fn new(
+        client: HttpClient,
+        network_type: &NetworkType,
+        primary_height: Option<HeightKind>,
+    ) -> Self {
+        let primary = primary_height.unwrap_or(match network_type {
+            NetworkType::LOCALNET => HeightKind::L2,
+            _ => HeightKind::L1,
+        });
+        ArbitrumAdapter { client, primary }
+    }
+}
+
+#[async_trait]
+impl ChainAdapter for ArbitrumAdapter {
+    async fn fetch_tip(&self, rpc: &str) -> AdapterResult<u64> {
+        self.fetch_by_tag(rpc, BlockTag::LATEST).await
+    }
+
+    async fn fetch_by_tag(&self, rpc: &str, tag: BlockTag) -> AdapterResult<u64> {
+        let block = json_rpc(
+            &self.client,
+            rpc,
+            "eth_getBlockByNumber",
+            json!([evm_block_tag(tag), false]),
+        )
+        .await?;
+
+        match self.primary {
+            HeightKind::L2 => parse_hex_u64(&block["number"]),
+            HeightKind::L1 => {
+                parse_hex_u64(&block["l1BlockNumber"]).map_err(|_| "Missing l1BlockNumber".into())
+            }
+        }
+    }
+
+    fn is_layer2(&self) -> bool {
+        true
+    }
+
+    async fn fetch_layer_heights(&self, rpc: &str) -> AdapterResult<LayerHeights> {
+        let block = json_rpc(
+            &self.client,
+            rpc,
+            "eth_getBlockByNumber",
+            json!([evm_block_tag(BlockTag::LATEST), false]),
+        )
+        .await?;
+
+        Ok(LayerHeights {
+            // plain devnets don't report an L1 block number
+            l1: parse_hex_u64(&block["l1BlockNumber"]).ok(),
+            l1_batch: None,
+            l2: parse_hex_u64(&block["number"])?,
+        })
+    }
+
+    // new heads carry the L2 number, which is only published when chosen as primary
+    fn tip_subscription(&self) -> Option<TipSubscription> {
+        (self.primary == HeightKind::L2).then(new_heads_subscription)
+    }
+
+    // a published L1 block number can't be resolved to an L1 block through an
+    // Arbitrum rpc, so headers are only tracked when the L2 height is published
+    fn supports_headers(&self) -> bool {
+        self.primary == HeightKind::L2
+    }
+
+    async fn fetch_header(&self, rpc: &str, height: u64) -> AdapterResult<BlockHeader> {
+        fetch_evm_header(&self.client, rpc, height).await
+    }
+
+    fn supports_balances(&self) -> bool {
+        true
+    }
+
+    async fn fetch_balances(
+        &self,
+        rpc: &str,
+        queries: &[BalanceQuery],
+    ) -> AdapterResult<Vec<Option<u128>>> {
+        fetch_evm_balances(&self.client, rpc, queries).await
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::Value;
+
+use super::{
+    http::HttpClient, rest_url, send_json, send_text, AdapterResult, BlockHeader, BlockTag,
+    ChainAdapter,
+};
+use crate::models::{
+    assets::{FinalityConfirmations, HttpConfig, RetryPolicy, RpcBackend, RpcEndpoint},
+    balances::{BalanceQuery, Utxo, UtxoAddressBalance},
+};
+
+pub use address::AddressFormat;
+use electrum::ElectrumConfig;
+
+mod address;
+mod bitcoind;
+mod electrum;
+
+/// Reads heights from Esplora REST apis, Bitcoin Core JSON-RPC or Electrum servers,
+/// depending on the `backend` of each rpc. Balances and UTXOs need an Esplora rpc.
+/// Serves bitcoin and its forks (litecoin, dogecoin, bitcoin cash), which only
+/// differ in their address formats. Filler addresses are checked against those
+/// formats when the config is loaded.
+pub struct BitcoinAdapter {
+    client: HttpClient,
+    confirmations: FinalityConfirmations,
+    endpoints: HashMap<String, RpcEndpoint>,
+    /// Set when any rpc is an Electrum server.
+    electrum: Option<ElectrumConfig>,
+}
+
+impl BitcoinAdapter {
+    pub fn new(
+        client: HttpClient,
+        confirmations: FinalityConfirmations,
+        http: &HttpConfig,
+        rpcs: &[RpcEndpoint],
+    ) -> AdapterResult<Self> {
+        // only Electrum needs a proxy that can tunnel raw connections
+        let electrum = rpcs
+            .iter()
+            .any(|rpc| rpc.backend == RpcBackend::ELECTRUM)
+            .then(|| ElectrumConfig::new(http))
+            .transpose()?;
+        Ok(BitcoinAdapter {
+            client,
+            confirmations,
+            endpoints: rpcs
+                .iter()
+                .map(|rpc| (rpc.url.clone(), rpc.clone()))
+                .collect(),
+            electrum,
+        })
+    }
+
+    fn electrum(&self, rpc: &str) -> (&ElectrumConfig, RetryPolicy) {
+        let config = self.electrum.as_ref().expect("electrum rpcs have a config");
+        (config, self.endpoints[rpc].retry)
+    }
+
+    fn backend(&self, rpc: &str) -> RpcBackend {
+        self.endpoints
+            .get(rpc)
+            .map(|endpoint| endpoint.backend)
+            .unwrap_or_default()
+    }
+
+    fn require_esplora(&self, rpc: &str) -> AdapterResult<()> {
+        match self.backend(rpc) {
+            RpcBackend::ESPLORA => Ok(()),
+            backend => Err(format!("{:?} rpcs don't serve address balances", backend).into()),
+        }
+    }
+}
+
+/// Funded minus spent output sums from an Esplora `chain_stats`/`mempool_stats` object.
+fn stats_balance(stats: &Value) -> AdapterResult<i64> {
+    let funded = stats["funded_txo_sum"]
+        .as_i64()
+        .ok_or("Missing funded_txo_sum")?;
+    let spent = stats["spent_txo_sum"]
+        .as_i64()
+        .ok_or("Missing spent_txo_sum")?;
+    Ok(funded - spent)
+}
+
+impl BitcoinAdapter {
+    /// Returns the confirmed balance and the net unconfirmed (mempool) change of
+    /// `address` in satoshis.
+    pub async fn fetch_address_stats(&self, rpc: &str, address: &str) -> AdapterResult<(u64, i64)> {
+        self.require_esplora(rpc)?;
+        let endpoint = rest_url(rpc, &format!("address/{}", address))?;
+        let info: Value = send_json(self.client.get(endpoint)).await?;
+        let confirmed = stats_balance(&info["chain_stats"])?.max(0) as u64;
+        let unconfirmed = stats_balance(&info["mempool_stats"])?;
+        Ok((confirmed, unconfirmed))
+    }
+
+    pub async fn fetch_utxos(&self, rpc: &str, address: &str) -> AdapterResult<Vec<Utxo>> {
+        self.require_esplora(rpc)?;
+        let endpoint = rest_url(rpc, &format!("address/{}/utxo", address))?;
+        send_json(self.client.get(endpoint)).await
+    }
+}
+
+#[async_trait]
+impl ChainAdapter for BitcoinAdapter {
+    async fn fetch_tip(&self, rpc: &str) -> AdapterResult<u64> {
+        match self.backend(rpc) {
+            RpcBackend::ESPLORA => {
+                let endpoint = rest_url(rpc, "blocks/tip/height")?;
+                Ok(send_text(self.client.get(endpoint)).await?.trim().parse()?)
+            }
+            RpcBackend::BITCOIND => {
+                let result =
+                    bitcoind::call(&self.client, &self.endpoints[rpc], "getblockcount", &[])
+                        .await?;
+                Ok(result.as_u64().ok_or("Invalid getblockcount response")?)
+            }
+            RpcBackend::ELECTRUM => {
+                let (config, retry) = self.electrum(rpc);
+                electrum::fetch_tip(config, rpc, retry).await
+            }
+        }
+    }
+
+    async fn fetch_by_tag(&self, rpc: &str, tag: BlockTag) -> AdapterResult<u64> {
+        // bitcoin has no finality, so safe and finalized are the tip minus a
+        // configured number of confirmations
+        let depth = match tag {
+            BlockTag::LATEST => 0,
+            BlockTag::SAFE => self.confirmations.safe,
+            BlockTag::FINALIZED => self.confirmations.finalized,
+        };
+        Ok(self.fetch_tip(rpc).await?.saturating_sub(depth))
+    }
+
+    fn supports_headers(&self) -> bool {
+        true
+    }
+
+    async fn fetch_header(&self, rpc: &str, height: u64) -> AdapterResult<BlockHeader> {
+        match self.backend(rpc) {
+            RpcBackend::ESPLORA => {
+                let hash = send_text(
+                    self.client
+                        .get(rest_url(rpc, &format!("block-height/{}", height))?),
+                )
+                .await?;
+                let block: Value = send_json(
+                    self.client
+                        .get(rest_url(rpc, &format!("block/{}", hash.trim()))?),
+                )
+                .await?;
+                Ok(BlockHeader {
+                    number: block["height"].as_u64().ok_or("Missing block height")?,
+                    hash: block["id"].as_str().ok_or("Missing block id")?.to_string(),
+                    // the genesis block has no previous block
+                    parent_hash: block["previousblockhash"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                    timestamp: block["timestamp"]
+                        .as_u64()
+                        .ok_or("Missing block timestamp")?,
+                })
+            }
+            RpcBackend::BITCOIND => {
+                bitcoind::fetch_header(&self.client, &self.endpoints[rpc], height).await
+            }
+            RpcBackend::ELECTRUM => {
+                let (config, retry) = self.electrum(rpc);
+                electrum::fetch_header(config, rpc, retry, height).await
+            }
+        }
+    }
+
+    fn supports_balances(&self) -> bool {
+        true
+    }
+
+    async fn fetch_balances(
+        &self,
+        rpc: &str,
+        queries: &[BalanceQuery],
+    ) -> AdapterResult<Vec<Option<u128>>> {
+        let mut balances = Vec::with_capacity(queries.len());
+        for query in queries {
+            let (confirmed, _) = self.fetch_address_stats(rpc, &query.address).await?;
+            balances.push(Some(confirmed as u128));
+        }
+        Ok(balances)
+    }
+
+    fn supports_utxos(&self) -> bool {
+        true
+    }
+
+    async fn fetch_utxo_balance(
+        &self,
+        rpc: &str,
+        address: &str,
+    ) -> AdapterResult<UtxoAddressBalance> {
+        let (confirmed, unconfirmed) = self.fetch_address_stats(rpc, address).await?;
+        let utxos = self.fetch_utxos(rpc, address).await?;
+        Ok(UtxoAddressBalance {
+            address: address.to_string(),
+            confirmed,
+            unconfirmed,
+            utxos,
+            updated_at: Utc::now(),
+        })
+    }
+}
@@ -0,0 +1,364 @@
+use sha2::{Digest, Sha256};
+
+use crate::{
+    chains::AdapterResult,
+    models::assets::{ChainFamily, Network, NetworkType},
+};
+
+const BASE58_CHARSET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const BECH32_CHARSET: &str = "qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+/// Checksum constants of segwit v0 (bech32) and v1+ (bech32m) addresses.
+const BECH32_CONST: u32 = 1;
+const BECH32M_CONST: u32 = 0x2bc830a3;
+
+/// Address encodings accepted on a UTXO chain and network: legacy base58check
+/// addresses by their leading character, plus segwit (bech32/bech32m) or CashAddr
+/// addresses by their human readable prefix. Checksums are verified, as are the
+/// witness program lengths of segwit addresses, but not base58 version bytes.
+#[derive(Debug, Clone, Copy)]
+pub struct AddressFormat {
+    base58_leads: &'static [char],
+    bech32_hrp: Option<&'static str>,
+    cashaddr_prefix: Option<&'static str>,
+}
+
+impl AddressFormat {
+    /// The format of `network`'s addresses, if it is a UTXO chain.
+    pub fn for_network(network: &Network) -> Option<Self> {
+        match network.chain_family {
+            ChainFamily::BITCOIN
+            | ChainFamily::LITECOIN
+            | ChainFamily::DOGECOIN
+            | ChainFamily::BITCOINCASH => {
+                Some(Self::new(network.chain_family, &network.network_type))
+            }
+            _ => None,
+        }
+    }
+
+    fn new(family: ChainFamily, network_type: &NetworkType) -> Self {
+        let (base58_leads, bech32_hrp, cashaddr_prefix): (&[char], _, _) =
+            match (family, network_type) {
+                (ChainFamily::LITECOIN, NetworkType::MAINNET) => {
+                    (&['L', 'M', '3'], Some("ltc"), None)
+                }
+                (ChainFamily::LITECOIN, NetworkType::TESTNET) => {
+                    (&['m', 'n', 'Q', '2'], Some("tltc"), None)
+                }
+                (ChainFamily::LITECOIN, NetworkType::LOCALNET) => {
+                    (&['m', 'n', 'Q', '2'], Some("rltc"), None)
+                }
+                // dogecoin never activated segwit
+                (ChainFamily::DOGECOIN, NetworkType::MAINNET) => (&['D', 'A', '9'], None, None),
+                (ChainFamily::DOGECOIN, _) => (&['n', 'm', '2'], None, None),
+                (ChainFamily::BITCOINCASH, NetworkType::MAINNET) => {
+                    (&['1', '3'], None, Some("bitcoincash"))
+                }
+                (ChainFamily::BITCOINCASH, NetworkType::TESTNET) => {
+                    (&['m', 'n', '2'], None, Some("bchtest"))
+                }
+                (ChainFamily::BITCOINCASH, NetworkType::LOCALNET) => {
+                    (&['m', 'n', '2'], None, Some("bchreg"))
+                }
+                (_, NetworkType::MAINNET) => (&['1', '3'], Some("bc"), None),
+                (_, NetworkType::TESTNET) => (&['m', 'n', '2'], Some("tb"), None),
+                (_, NetworkType::LOCALNET) => (&['m', 'n', '2'], Some("bcrt"), None),
+            };
+        AddressFormat {
+            base58_leads,
+            bech32_hrp,
+            cashaddr_prefix,
+        }
+    }
+
+    /// Checks that `address` belongs to this chain and network and returns it in the
+    /// form sent to the rpc. Segwit and CashAddr addresses must be all lowercase or
+    /// all uppercase, and are returned lowercase; CashAddr addresses given without
+    /// their prefix get it added.
+    pub fn normalize(&self, address: &str) -> AdapterResult<String> {
+        if address.starts_with(self.base58_leads) && is_base58check(address) {
+            return Ok(address.to_string());
+        }
+
+        if !is_single_case(address) {
+            return Err(format!("{} mixes upper and lower case", address).into());
+        }
+
+        if let Some(hrp) = self.bech32_hrp {
+            let lowercase = address.to_lowercase();
+            if let Some(data) = lowercase
+                .strip_prefix(hrp)
+                .and_then(|rest| rest.strip_prefix('1'))
+            {
+                if is_segwit(hrp, data) {
+                    return Ok(lowercase);
+                }
+            }
+        }
+
+        if let Some(prefix) = self.cashaddr_prefix {
+            let lowercase = address.to_lowercase();
+            let data = match lowercase.split_once(':') {
+                Some((given, data)) if given == prefix => data,
+                Some(_) => "",
+                None => lowercase.as_str(),
+            };
+            if is_cashaddr(prefix, data) {
+                return Ok(format!("{}:{}", prefix, data));
+            }
+        }
+
+        Err(format!("{} is not a valid address for this chain", address).into())
+    }
+}
+
+/// Whether `address` is a 25 byte base58 payload ending in the first four bytes of
+/// its double SHA-256.
+fn is_base58check(address: &str) -> bool {
+    if !(25..=35).contains(&address.len()) {
+        return false;
+    }
+    // big-endian base 256 digits of the base58 number
+    let mut bytes: Vec<u8> = Vec::new();
+    for c in address.chars() {
+        let Some(digit) = BASE58_CHARSET.find(c) else {
+            return false;
+        };
+        let mut carry = digit as u32;
+        for byte in bytes.iter_mut().rev() {
+            carry += *byte as u32 * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.insert(0, carry as u8);
+            carry >>= 8;
+        }
+    }
+    // each leading '1' stands for a zero byte
+    let zeros = address.chars().take_while(|c| *c == '1').count();
+    let mut payload = vec![0; zeros];
+    payload.extend(bytes);
+
+    if payload.len() != 25 {
+        return false;
+    }
+    let (body, checksum) = payload.split_at(21);
+    Sha256::digest(Sha256::digest(body))[..4] == *checksum
+}
+
+fn is_single_case(address: &str) -> bool {
+    !address.chars().any(|c| c.is_ascii_lowercase())
+        || !address.chars().any(|c| c.is_ascii_uppercase())
+}
+
+/// The 5-bit values of a bech32 data part.
+fn bech32_values(data: &str) -> Option<Vec<u8>> {
+    data.chars()
+        .map(|c| BECH32_CHARSET.find(c).map(|value| value as u8))
+        .collect()
+}
+
+fn bech32_polymod(values: impl IntoIterator<Item = u8>) -> u32 {
+    const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+    let mut checksum = 1u32;
+    for value in values {
+        let top = checksum >> 25;
+        checksum = ((checksum & 0x1ffffff) << 5) ^ value as u32;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum
+}
+
+/// Whether `data` is the data part of a segwit address under `hrp`: bech32 for
+/// witness version 0 and bech32m for later versions, with a witness program of 20
+/// (P2WPKH) or 32 (P2WSH) bytes for version 0, 32 (P2TR) for version 1, and 2 to 40
+/// for later versions.
+fn is_segwit(hrp: &str, data: &str) -> bool {
+    let Some(values) = bech32_values(data) else {
+        return false;
+    };
+    if values.len() < 6 + 1 || values[0] > 16 {
+        return false;
+    }
+    let Some(program) = witness_program(&values[1..values.len() - 6]) else {
+        return false;
+    };
+    let valid_length = match values[0] {
+        0 => program.len() == 20 || program.len() == 32,
+        1 => program.len() == 32,
+        _ => (2..=40).contains(&program.len()),
+    };
+    if !valid_length {
+        return false;
+    }
+    let expanded = hrp
+        .bytes()
+        .map(|b| b >> 5)
+        .chain([0])
+        .chain(hrp.bytes().map(|b| b & 31));
+    let expected = if values[0] == 0 {
+        BECH32_CONST
+    } else {
+        BECH32M_CONST
+    };
+    bech32_polymod(expanded.chain(values.iter().copied())) == expected
+}
+
+/// Regroups the 5-bit `values` of a witness program into bytes, rejecting more than
+/// four bits of padding or padding that isn't zero.
+fn witness_program(values: &[u8]) -> Option<Vec<u8>> {
+    let mut program = Vec::with_capacity(values.len() * 5 / 8);
+    let (mut acc, mut bits) = (0u32, 0);
+    for value in values {
+        acc = (acc << 5) | *value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            program.push((acc >> bits) as u8);
+        }
+    }
+    (bits < 5 && acc & ((1 << bits) - 1) == 0).then_some(program)
+}
+
+/// Whether `data` is the payload and 40-bit checksum of a CashAddr under `prefix`.
+fn is_cashaddr(prefix: &str, data: &str) -> bool {
+    const GENERATOR: [u64; 5] = [
+        0x98f2bc8e61,
+        0x79b76d99e2,
+        0xf33e5fb3c4,
+        0xae2eabe2a8,
+        0x1e4f43e470,
+    ];
+    let Some(values) = bech32_values(data) else {
+        return false;
+    };
+    if values.len() < 8 + 34 {
+        return false;
+    }
+    let mut checksum = 1u64;
+    let expanded = prefix.bytes().map(|b| b & 31).chain([0]);
+    for value in expanded.chain(values.iter().copied()) {
+        let top = checksum >> 35;
+        checksum = ((checksum & 0x07ffffffff) << 5) ^ value as u64;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum ^ 1 == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_addresses_of_its_own_chain() {
+        let litecoin = AddressFormat::new(ChainFamily::LITECOIN, &NetworkType::MAINNET);
+        assert!(litecoin
+            .normalize("ltc1qqqqsyqcyq5rqwzqfpg9scrgwpugpzysn3s44dy")
+            .is_ok());
+        assert!(litecoin
+            .normalize("LVuDpNCSSj6pQ7t9Pv6d6sUkLKoqDEVUnJ")
+            .is_ok());
+        assert!(litecoin
+            .normalize("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq")
+            .is_err());
+
+        let dogecoin = AddressFormat::new(ChainFamily::DOGECOIN, &NetworkType::MAINNET);
+        assert!(dogecoin
+            .normalize("DH5yaieqoZN36fDVciNyRueRGvGLR3mr7L")
+            .is_ok());
+        assert!(dogecoin
+            .normalize("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2")
+            .is_err());
+    }
+
+    #[test]
+    fn prefixes_cashaddr_addresses() {
+        let bitcoin_cash = AddressFormat::new(ChainFamily::BITCOINCASH, &NetworkType::MAINNET);
+        let address = "qpm2qsznhks23z7629mms6s4cwef74vcwvy22gdx6a";
+        assert_eq!(
+            bitcoin_cash.normalize(address).unwrap(),
+            format!("bitcoincash:{}", address)
+        );
+        assert!(bitcoin_cash
+            .normalize(&format!("bchtest:{}", address))
+            .is_err());
+    }
+
+    #[test]
+    fn rejects_bad_checksums() {
+        let bitcoin = AddressFormat::new(ChainFamily::BITCOIN, &NetworkType::MAINNET);
+        assert!(bitcoin
+            .normalize("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq")
+            .is_ok());
+        assert!(bitcoin
+            .normalize("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdz")
+            .is_err());
+        assert!(bitcoin
+            .normalize("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2")
+            .is_ok());
+        assert!(bitcoin
+            .normalize("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN3")
+            .is_err());
+        // taproot addresses use the bech32m checksum
+        assert!(bitcoin
+            .normalize("bc1p5d7rjq7g6rdk2yhzks9smlaqtedr4dekq08ge8ztwac72sfr9rusxg3297")
+            .is_ok());
+
+        let bitcoin_cash = AddressFormat::new(ChainFamily::BITCOINCASH, &NetworkType::MAINNET);
+        assert!(bitcoin_cash
+            .normalize("qpm2qsznhks23z7629mms6s4cwef74vcwvy22gdx6b")
+            .is_err());
+    }
+
+    #[test]
+    fn rejects_mixed_case_segwit_addresses() {
+        let bitcoin = AddressFormat::new(ChainFamily::BITCOIN, &NetworkType::MAINNET);
+        assert_eq!(
+            bitcoin
+                .normalize("BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4")
+                .unwrap(),
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"
+        );
+        assert!(bitcoin
+            .normalize("bc1qW508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4")
+            .is_err());
+
+        let testnet = AddressFormat::new(ChainFamily::BITCOIN, &NetworkType::TESTNET);
+        assert!(testnet
+            .normalize("tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7")
+            .is_ok());
+        assert!(testnet
+            .normalize("tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sL5k7")
+            .is_err());
+    }
+
+    #[test]
+    fn checks_witness_program_lengths() {
+        let bitcoin = AddressFormat::new(ChainFamily::BITCOIN, &NetworkType::MAINNET);
+        // a 32 byte version 0 program (P2WSH)
+        assert!(bitcoin
+            .normalize("bc1qqqqsyqcyq5rqwzqfpg9scrgwpugpzysnzs23v9ccrydpk8qarc0szrtjt7")
+            .is_ok());
+        // a 16 byte version 0 program
+        assert!(bitcoin
+            .normalize("BC1QR508D6QEJXTDG4Y5R3ZARVARYV98GJ9P")
+            .is_err());
+        // a 20 byte version 1 program, where taproot takes 32 bytes
+        assert!(bitcoin
+            .normalize("bc1pqqqsyqcyq5rqwzqfpg9scrgwpugpzysntwgkaa")
+            .is_err());
+        // later versions take 2 to 40 bytes
+        assert!(bitcoin
+            .normalize("bc1zqqqsyqcyq5rqwzqfpg9scrgwpugpzysnk9ct2q")
+            .is_ok());
+    }
+}
@@ -0,0 +1,109 @@
+use serde_json::{json, Value};
+
+use crate::{
+    chains::{http::HttpClient, send_json, AdapterResult, BlockHeader, JsonRpcError},
+    models::assets::RpcEndpoint,
+};
+
+/// Sends a Bitcoin Core JSON-RPC call. The client authenticates with the endpoint's
+/// credentials when set (otherwise with any credentials in the url).
+pub async fn call(
+    client: &HttpClient,
+    endpoint: &RpcEndpoint,
+    method: &str,
+    params: &[Value],
+) -> AdapterResult<Value> {
+    let payload = json!({
+        "jsonrpc": "1.0",
+        "id": 1,
+        "method": method,
+        "params": params
+    });
+
+    let mut res: Value = send_json(client.post(&endpoint.url).json(&payload)).await?;
+
+    // bitcoind always sets `error`, to null on success
+    if !res["error"].is_null() {
+        return Err(JsonRpcError::new(method, res["error"].take()).into());
+    }
+    Ok(res["result"].take())
+}
+
+pub async fn fetch_header(
+    client: &HttpClient,
+    endpoint: &RpcEndpoint,
+    height: u64,
+) -> AdapterResult<BlockHeader> {
+    let hash = call(client, endpoint, "getblockhash", &[json!(height)]).await?;
+    let header = call(client, endpoint, "getblockheader", &[hash, json!(true)]).await?;
+    Ok(BlockHeader {
+        number: header["height"].as_u64().ok_or("Missing block height")?,
+        hash: header["hash"]
+            .as_str()
+            .ok_or("Missing block hash")?
+            .to_string(),
+        // the genesis block has no previous block
+        parent_hash: header["previousblockhash"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+        timestamp: header["time"].as_u64().ok_or("Missing block time")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    };
+
+    use axum::{http::StatusCode, routing::post, Json, Router};
+
+    use super::*;
+    use crate::{
+        chains::mock_server::serve,
+        models::assets::{HttpConfig, RetryPolicy, RpcBackend},
+    };
+
+    #[tokio::test]
+    async fn reports_errors_sent_with_a_500_without_retrying() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let counter = calls.clone();
+        // bitcoind answers failed calls with a 500 and the error in the body
+        let base = serve(Router::new().route(
+            "/",
+            post(move || async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(json!({
+                        "result": null,
+                        "error": { "code": -32601, "message": "Method not found" },
+                        "id": 1
+                    })),
+                )
+            }),
+        ))
+        .await;
+        let endpoint = RpcEndpoint {
+            url: base,
+            backend: RpcBackend::BITCOIND,
+            username: None,
+            password: None,
+            headers: Default::default(),
+            retry: RetryPolicy {
+                attempts: 3,
+                backoff_ms: 1,
+            },
+            ws: None,
+        };
+        let client =
+            HttpClient::new(&HttpConfig::default(), std::slice::from_ref(&endpoint)).unwrap();
+        let error = call(&client, &endpoint, "getblockcount", &[])
+            .await
+            .unwrap_err();
+        assert!(JsonRpcError::is_method_not_found(error.as_ref()));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
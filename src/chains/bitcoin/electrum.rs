@@ -0,0 +1,258 @@
+use std::time::Duration;
+
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    time,
+};
+
+use crate::{
+    chains::{
+        http::{connect_tcp, TcpProxy},
+        AdapterResult, BlockHeader, JsonRpcError,
+    },
+    models::assets::{HttpConfig, RetryPolicy},
+};
+
+const PROTOCOL_VERSION: &str = "1.4";
+
+/// The network's http settings, applied to the raw connections Electrum is spoken over.
+pub struct ElectrumConfig {
+    connect_timeout: Duration,
+    request_timeout: Duration,
+    proxy: Option<TcpProxy>,
+}
+
+impl ElectrumConfig {
+    pub fn new(config: &HttpConfig) -> AdapterResult<Self> {
+        Ok(ElectrumConfig {
+            connect_timeout: Duration::from_millis(config.connect_timeout_ms),
+            request_timeout: Duration::from_millis(config.request_timeout_ms),
+            proxy: TcpProxy::from_config(config)?,
+        })
+    }
+}
+
+/// Sends one Electrum request to a `tcp://host:port` or `ssl://host:port` server and
+/// returns its `result`. Failures other than an error answered by the server are
+/// retried with `retry`'s backoff.
+async fn call(
+    config: &ElectrumConfig,
+    rpc: &str,
+    retry: RetryPolicy,
+    method: &str,
+    params: Value,
+) -> AdapterResult<Value> {
+    let mut backoff = Duration::from_millis(retry.backoff_ms);
+    let mut attempt = 1;
+    loop {
+        match call_once(config, rpc, method, params.clone()).await {
+            Err(e) if attempt < retry.attempts && !e.is::<JsonRpcError>() => {
+                time::sleep(backoff).await;
+                backoff *= 2;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Sends the request over a fresh connection.
+async fn call_once(
+    config: &ElectrumConfig,
+    rpc: &str,
+    method: &str,
+    params: Value,
+) -> AdapterResult<Value> {
+    let (tls, address) = match rpc.split_once("://") {
+        Some(("tcp", address)) => (false, address),
+        Some(("ssl", address)) => (true, address),
+        _ => return Err("Electrum rpcs must start with tcp:// or ssl://".into()),
+    };
+    let address = address.trim_end_matches('/');
+    let (host, port) = address
+        .rsplit_once(':')
+        .and_then(|(host, port)| Some((host, port.parse::<u16>().ok()?)))
+        .ok_or("Electrum rpcs must include a port")?;
+
+    time::timeout(config.request_timeout, async {
+        let stream = time::timeout(
+            config.connect_timeout,
+            connect_tcp(host, port, config.proxy.as_ref()),
+        )
+        .await
+        .map_err(|_| "Timed out connecting to Electrum server")??;
+        if !tls {
+            return exchange(stream, method, params).await;
+        }
+        let connector = tokio_native_tls::TlsConnector::from(
+            tokio_native_tls::native_tls::TlsConnector::new()?,
+        );
+        let stream = connector.connect(host, stream).await?;
+        exchange(stream, method, params).await
+    })
+    .await
+    .map_err(|_| "Electrum request timed out")?
+}
+
+/// Negotiates the protocol version, as servers expect it to be the first message,
+/// then sends the request and waits for its response.
+async fn exchange<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: S,
+    method: &str,
+    params: Value,
+) -> AdapterResult<Value> {
+    let mut stream = BufReader::new(stream);
+    let requests = [
+        json!({ "jsonrpc": "2.0", "id": 0, "method": "server.version", "params": ["garden", PROTOCOL_VERSION] }),
+        json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }),
+    ];
+    for request in requests {
+        let mut line = serde_json::to_vec(&request)?;
+        line.push(b'\n');
+        stream.write_all(&line).await?;
+    }
+    stream.flush().await?;
+
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if stream.read_line(&mut line).await? == 0 {
+            return Err("Electrum server closed the connection".into());
+        }
+        let mut response: Value = serde_json::from_str(&line)?;
+        // skip the version reply and any notifications
+        if response["id"] != 1 {
+            continue;
+        }
+        if let Some(error) = response.get("error").filter(|error| !error.is_null()) {
+            return Err(JsonRpcError::new(method, error.clone()).into());
+        }
+        return Ok(response["result"].take());
+    }
+}
+
+pub async fn fetch_tip(
+    config: &ElectrumConfig,
+    rpc: &str,
+    retry: RetryPolicy,
+) -> AdapterResult<u64> {
+    let tip = call(
+        config,
+        rpc,
+        retry,
+        "blockchain.headers.subscribe",
+        json!([]),
+    )
+    .await?;
+    Ok(tip["height"]
+        .as_u64()
+        .ok_or("Invalid blockchain.headers.subscribe response")?)
+}
+
+/// Electrum only serves raw 80-byte headers, so the hashes and time are decoded from
+/// the header itself.
+pub async fn fetch_header(
+    config: &ElectrumConfig,
+    rpc: &str,
+    retry: RetryPolicy,
+    height: u64,
+) -> AdapterResult<BlockHeader> {
+    let raw = call(
+        config,
+        rpc,
+        retry,
+        "blockchain.block.header",
+        json!([height]),
+    )
+    .await?;
+    let header = hex::decode(raw.as_str().ok_or("Invalid block header response")?)?;
+    if header.len() != 80 {
+        return Err("Block header must be 80 bytes".into());
+    }
+
+    // hashes are displayed byte-reversed
+    let display_hash = |bytes: &[u8]| {
+        let mut bytes = bytes.to_vec();
+        bytes.reverse();
+        hex::encode(bytes)
+    };
+    let hash = Sha256::digest(Sha256::digest(&header));
+    let timestamp = u32::from_le_bytes(header[68..72].try_into()?);
+
+    Ok(BlockHeader {
+        number: height,
+        hash: display_hash(&hash),
+        parent_hash: display_hash(&header[4..36]),
+        timestamp: timestamp as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    };
+
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// An Electrum server that drops the first connection, then answers the tip
+    /// request with `reply`. Returns its url and a count of connections.
+    async fn mock_server(reply: Value) -> (String, Arc<AtomicU32>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("tcp://{}", listener.local_addr().unwrap());
+        let connections = Arc::new(AtomicU32::new(0));
+        let counter = connections.clone();
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                    continue;
+                }
+                let mut socket = BufReader::new(socket);
+                let mut line = String::new();
+                // the version negotiation, then the request
+                for _ in 0..2 {
+                    line.clear();
+                    socket.read_line(&mut line).await.unwrap();
+                }
+                let mut response = json!({ "jsonrpc": "2.0", "id": 1 });
+                response
+                    .as_object_mut()
+                    .unwrap()
+                    .extend(reply.as_object().unwrap().clone());
+                let mut response = serde_json::to_vec(&response).unwrap();
+                response.push(b'\n');
+                socket.write_all(&response).await.unwrap();
+            }
+        });
+        (url, connections)
+    }
+
+    fn retry() -> RetryPolicy {
+        RetryPolicy {
+            attempts: 3,
+            backoff_ms: 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_dropped_connections() {
+        let (url, connections) = mock_server(json!({ "result": { "height": 840000 } })).await;
+        let config = ElectrumConfig::new(&HttpConfig::default()).unwrap();
+        assert_eq!(fetch_tip(&config, &url, retry()).await.unwrap(), 840000);
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_server_errors() {
+        let (url, connections) =
+            mock_server(json!({ "error": { "code": 1, "message": "unsupported" } })).await;
+        let config = ElectrumConfig::new(&HttpConfig::default()).unwrap();
+        assert!(fetch_tip(&config, &url, retry()).await.is_err());
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+    }
+}
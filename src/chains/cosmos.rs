@@ -0,0 +1,76 @@
+use async_trait::async_trait;
+use serde_json::Value;
+
+use super::{
+    http::HttpClient, parse_u64, rest_url, send_json, AdapterResult, BlockTag, ChainAdapter,
+};
+
+/// Cosmos SDK chains report `latest_block_height` from the Tendermint/CometBFT rpc's
+/// `/status`. Blocks are final once committed, so every tag resolves to the same
+/// height.
+pub struct CosmosAdapter {
+    client: HttpClient,
+}
+
+impl CosmosAdapter {
+    pub fn new(client: HttpClient) -> Self {
+        CosmosAdapter { client }
+    }
+}
+
+#[async_trait]
+impl ChainAdapter for CosmosAdapter {
+    async fn fetch_tip(&self, rpc: &str) -> AdapterResult<u64> {
+        let status: Value = send_json(self.client.get(rest_url(rpc, "status")?)).await?;
+        // older tendermint versions return the result without a JSON-RPC envelope
+        let result = status.get("result").unwrap_or(&status);
+        parse_u64(&result["sync_info"]["latest_block_height"])
+    }
+
+    async fn fetch_by_tag(&self, rpc: &str, _tag: BlockTag) -> AdapterResult<u64> {
+        self.fetch_tip(rpc).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{routing::get, Json, Router};
+    use serde_json::json;
+
+    use super::*;
+    use crate::chains::mock_server::serve;
+
+    #[tokio::test]
+    async fn fetches_latest_block_height() {
+        let rpc = serve(Router::new().route(
+            "/status",
+            get(|| async {
+                Json(json!({
+                    "jsonrpc": "2.0",
+                    "id": -1,
+                    "result": {
+                        "node_info": { "network": "cosmoshub-4" },
+                        "sync_info": {
+                            "latest_block_height": "23412598",
+                            "catching_up": false
+                        }
+                    }
+                }))
+            }),
+        ))
+        .await;
+        let adapter = CosmosAdapter::new(HttpClient::default());
+        assert_eq!(adapter.fetch_tip(&rpc).await.unwrap(), 23412598);
+    }
+
+    #[tokio::test]
+    async fn fetches_height_without_envelope() {
+        let rpc = serve(Router::new().route(
+            "/status",
+            get(|| async { Json(json!({ "sync_info": { "latest_block_height": "812" } })) }),
+        ))
+        .await;
+        let adapter = CosmosAdapter::new(HttpClient::default());
+        assert_eq!(adapter.fetch_tip(&format!("{}/", rpc)).await.unwrap(), 812);
+    }
+}
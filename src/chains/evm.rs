@@ -0,0 +1,258 @@
+use std::num::{IntErrorKind, ParseIntError};
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+use super::{
+    http::HttpClient, json_rpc, json_rpc_batch, parse_hex_u128, parse_hex_u64, AdapterResult,
+    BlockHeader, BlockTag, ChainAdapter, TipSubscription,
+};
+use crate::models::balances::BalanceQuery;
+
+/// `balanceOf(address)` selector.
+const BALANCE_OF_SELECTOR: &str = "70a08231";
+const ZERO_ADDRESS: &str = "0x0000000000000000000000000000000000000000";
+
+pub struct EvmAdapter {
+    client: HttpClient,
+}
+
+impl EvmAdapter {
+    pub fn new(client: HttpClient) -> Self {
+        EvmAdapter { client }
+    }
+}
+
+pub fn evm_block_tag(tag: BlockTag) -> &'static str {
+    match tag {
+        BlockTag::LATEST => "latest",
+        BlockTag::SAFE => "safe",
+        BlockTag::FINALIZED => "finalized",
+    }
+}
+
+/// Returns the number of the block identified by `tag`.
+pub async fn fetch_evm_block_number(
+    client: &HttpClient,
+    rpc: &str,
+    tag: BlockTag,
+) -> AdapterResult<u64> {
+    let block = json_rpc(
+        client,
+        rpc,
+        "eth_getBlockByNumber",
+        json!([evm_block_tag(tag), false]),
+    )
+    .await?;
+    parse_hex_u64(&block["number"])
+}
+
+/// Fetches the header of block `height` with `eth_getBlockByNumber`.
+pub async fn fetch_evm_header(
+    client: &HttpClient,
+    rpc: &str,
+    height: u64,
+) -> AdapterResult<BlockHeader> {
+    let block: Value = json_rpc(
+        client,
+        rpc,
+        "eth_getBlockByNumber",
+        json!([format!("0x{:x}", height), false]),
+    )
+    .await?;
+    if block.is_null() {
+        return Err(format!("Block {} not found", height).into());
+    }
+    Ok(BlockHeader {
+        number: parse_hex_u64(&block["number"])?,
+        hash: block["hash"]
+            .as_str()
+            .ok_or("Missing block hash")?
+            .to_string(),
+        parent_hash: block["parentHash"]
+            .as_str()
+            .ok_or("Missing parent hash")?
+            .to_string(),
+        timestamp: parse_hex_u64(&block["timestamp"])?,
+    })
+}
+
+/// Native assets are configured with `primary` (or the zero address) as their token address.
+pub fn is_native_token(token_address: &str) -> bool {
+    token_address.eq_ignore_ascii_case("primary")
+        || token_address.eq_ignore_ascii_case(ZERO_ADDRESS)
+}
+
+pub fn balance_of_calldata(address: &str) -> String {
+    format!(
+        "0x{}{:0>64}",
+        BALANCE_OF_SELECTOR,
+        address.trim_start_matches("0x").to_lowercase()
+    )
+}
+
+/// Reads native balances with `eth_getBalance` and ERC-20 balances with `balanceOf`,
+/// sending the queries for the chain in as few JSON-RPC batch requests as
+/// `maxBatchSize` allows.
+pub async fn fetch_evm_balances(
+    client: &HttpClient,
+    rpc: &str,
+    queries: &[BalanceQuery],
+) -> AdapterResult<Vec<Option<u128>>> {
+    let calls: Vec<(&str, serde_json::Value)> = queries
+        .iter()
+        .map(|query| {
+            if is_native_token(&query.token_address) {
+                ("eth_getBalance", json!([query.address, "latest"]))
+            } else {
+                (
+                    "eth_call",
+                    json!([
+                        { "to": query.token_address, "data": balance_of_calldata(&query.address) },
+                        "latest"
+                    ]),
+                )
+            }
+        })
+        .collect();
+
+    json_rpc_batch(client, rpc, &calls)
+        .await?
+        .iter()
+        .map(|value| match parse_hex_u128(value) {
+            Ok(amount) => Ok(Some(amount)),
+            // balanceOf returns a uint256, which can exceed a u128
+            Err(e) if is_overflow(e.as_ref()) => Ok(None),
+            Err(e) => Err(e),
+        })
+        .collect()
+}
+
+fn is_overflow(error: &(dyn std::error::Error + 'static)) -> bool {
+    error
+        .downcast_ref::<ParseIntError>()
+        .is_some_and(|e| *e.kind() == IntErrorKind::PosOverflow)
+}
+
+/// `eth_subscribe("newHeads")`, which pushes the header of every new block.
+pub fn new_heads_subscription() -> TipSubscription {
+    TipSubscription {
+        method: "eth_subscribe",
+        params: json!(["newHeads"]),
+        parse: |head| parse_hex_u64(&head["number"]),
+    }
+}
+
+#[async_trait]
+impl ChainAdapter for EvmAdapter {
+    async fn fetch_tip(&self, rpc: &str) -> AdapterResult<u64> {
+        let result = json_rpc(&self.client, rpc, "eth_blockNumber", json!([])).await?;
+        parse_hex_u64(&result)
+    }
+
+    async fn fetch_by_tag(&self, rpc: &str, tag: BlockTag) -> AdapterResult<u64> {
+        fetch_evm_block_number(&self.client, rpc, tag).await
+    }
+
+    fn tip_subscription(&self) -> Option<TipSubscription> {
+        Some(new_heads_subscription())
+    }
+
+    fn supports_headers(&self) -> bool {
+        true
+    }
+
+    async fn fetch_header(&self, rpc: &str, height: u64) -> AdapterResult<BlockHeader> {
+        fetch_evm_header(&self.client, rpc, height).await
+    }
+
+    fn supports_balances(&self) -> bool {
+        true
+    }
+
+    async fn fetch_balances(
+        &self,
+        rpc: &str,
+        queries: &[BalanceQuery],
+    ) -> AdapterResult<Vec<Option<u128>>> {
+        fetch_evm_balances(&self.client, rpc, queries).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{routing::post, Json, Router};
+    use serde_json::json;
+
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::{chains::mock_server::serve, models::assets::HttpConfig};
+
+    #[tokio::test]
+    async fn reports_balances_above_u128_as_none() {
+        let base = serve(Router::new().route(
+            "/",
+            post(|Json(calls): Json<Vec<Value>>| async move {
+                let results = ["0x2a", "0x10000000000000000000000000000000000000000"];
+                Json(
+                    calls
+                        .iter()
+                        .zip(results)
+                        .map(|(call, result)| json!({ "jsonrpc": "2.0", "id": call["id"], "result": result }))
+                        .collect::<Vec<_>>(),
+                )
+            }),
+        ))
+        .await;
+        let queries = ["0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48", ZERO_ADDRESS].map(|token| {
+            BalanceQuery {
+                token_address: token.to_string(),
+                address: "0x00000000000000000000000000000000000000f1".to_string(),
+            }
+        });
+        let balances = fetch_evm_balances(&HttpClient::default(), &base, &queries)
+            .await
+            .unwrap();
+        assert_eq!(balances, vec![Some(42), None]);
+    }
+
+    #[tokio::test]
+    async fn splits_balance_queries_into_bounded_batches() {
+        let sizes = Arc::new(Mutex::new(Vec::new()));
+        let recorded = sizes.clone();
+        let base = serve(Router::new().route(
+            "/",
+            post(move |Json(calls): Json<Vec<Value>>| async move {
+                recorded.lock().unwrap().push(calls.len());
+                // answer in reverse, as batch responses may come back in any order
+                Json(
+                    calls
+                        .iter()
+                        .rev()
+                        .map(|call| {
+                            let balance =
+                                format!("0x{:x}", call["params"][0].as_str().unwrap().len());
+                            json!({ "jsonrpc": "2.0", "id": call["id"], "result": balance })
+                        })
+                        .collect::<Vec<_>>(),
+                )
+            }),
+        ))
+        .await;
+        let queries: Vec<BalanceQuery> = (1..=5)
+            .map(|length| BalanceQuery {
+                token_address: ZERO_ADDRESS.to_string(),
+                address: "f".repeat(length),
+            })
+            .collect();
+        let config = HttpConfig {
+            max_batch_size: 2,
+            ..HttpConfig::default()
+        };
+        let client = HttpClient::new(&config, &[]).unwrap();
+        let balances = fetch_evm_balances(&client, &base, &queries).await.unwrap();
+        assert_eq!(balances, (1..=5).map(Some).collect::<Vec<_>>());
+        assert_eq!(*sizes.lock().unwrap(), vec![2, 2, 1]);
+    }
+}
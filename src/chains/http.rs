@@ -0,0 +1,364 @@
+use std::{sync::Arc, time::Duration};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Response, StatusCode,
+};
+use serde::Serialize;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+use super::AdapterResult;
+use crate::models::assets::{HttpConfig, RetryPolicy, RpcEndpoint};
+
+/// Upper bound on the size of a proxy's reply to `CONNECT`.
+const MAX_PROXY_RESPONSE: usize = 8192;
+
+/// Sends the http requests of one network, applying the headers, credentials and
+/// retry policy of whichever configured rpc a request goes to.
+#[derive(Clone)]
+pub struct HttpClient {
+    /// Used for urls that don't belong to any configured rpc.
+    default: reqwest::Client,
+    rpcs: Arc<Vec<RpcClient>>,
+    max_batch_size: usize,
+}
+
+impl Default for HttpClient {
+    fn default() -> Self {
+        HttpClient {
+            default: reqwest::Client::default(),
+            rpcs: Arc::default(),
+            max_batch_size: HttpConfig::default().max_batch_size,
+        }
+    }
+}
+
+struct RpcClient {
+    /// The rpc url without its query string or trailing slash; request urls built
+    /// from the rpc url all start with it.
+    base: String,
+    client: reqwest::Client,
+    username: Option<String>,
+    password: Option<String>,
+    retry: RetryPolicy,
+}
+
+impl HttpClient {
+    pub fn new(config: &HttpConfig, rpcs: &[RpcEndpoint]) -> AdapterResult<Self> {
+        if config.max_batch_size == 0 {
+            return Err("maxBatchSize must be at least 1".into());
+        }
+        let builder = || {
+            let mut builder = reqwest::Client::builder()
+                .connect_timeout(Duration::from_millis(config.connect_timeout_ms))
+                .timeout(Duration::from_millis(config.request_timeout_ms));
+            if let Some(proxy) = &config.proxy {
+                // the proxy url may hold credentials, so it's left out of the error
+                let proxy = reqwest::Proxy::all(proxy).map_err(|_| "Invalid proxy url")?;
+                builder = builder.proxy(proxy);
+            }
+            AdapterResult::Ok(builder)
+        };
+
+        let mut clients = Vec::with_capacity(rpcs.len());
+        for rpc in rpcs {
+            clients.push(RpcClient {
+                base: rpc_base(&rpc.url),
+                client: builder()?.default_headers(rpc_headers(rpc)?).build()?,
+                username: rpc.username.clone(),
+                password: rpc.password.clone(),
+                retry: rpc.retry,
+            });
+        }
+
+        Ok(HttpClient {
+            default: builder()?.build()?,
+            rpcs: Arc::new(clients),
+            max_batch_size: config.max_batch_size,
+        })
+    }
+
+    /// Most calls to send in one JSON-RPC batch request.
+    pub fn max_batch_size(&self) -> usize {
+        self.max_batch_size
+    }
+
+    pub fn get(&self, url: impl AsRef<str>) -> RpcRequest {
+        self.request(reqwest::Method::GET, url.as_ref())
+    }
+
+    pub fn post(&self, url: impl AsRef<str>) -> RpcRequest {
+        self.request(reqwest::Method::POST, url.as_ref())
+    }
+
+    fn request(&self, method: reqwest::Method, url: &str) -> RpcRequest {
+        let normalized = normalize(url);
+        // the most specific rpc wins when one rpc url is a prefix of another
+        let rpc = self
+            .rpcs
+            .iter()
+            .filter(|rpc| {
+                normalized
+                    .strip_prefix(&rpc.base)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with(['/', '?']))
+            })
+            .max_by_key(|rpc| rpc.base.len());
+        match rpc {
+            Some(rpc) => {
+                let mut builder = rpc.client.request(method, url);
+                if let Some(username) = &rpc.username {
+                    builder = builder.basic_auth(username, rpc.password.as_ref());
+                }
+                RpcRequest {
+                    builder,
+                    retry: rpc.retry,
+                }
+            }
+            None => RpcRequest {
+                builder: self.default.request(method, url),
+                retry: RetryPolicy::default(),
+            },
+        }
+    }
+}
+
+/// The configured headers of `rpc`, marked sensitive so they're kept out of logs.
+pub fn rpc_headers(rpc: &RpcEndpoint) -> AdapterResult<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    for (name, value) in &rpc.headers {
+        let name =
+            HeaderName::try_from(name).map_err(|_| format!("Invalid header name {}", name))?;
+        let mut value = HeaderValue::try_from(value)
+            .map_err(|_| format!("Invalid value for header {}", name))?;
+        value.set_sensitive(true);
+        headers.insert(name, value);
+    }
+    Ok(headers)
+}
+
+/// A `Basic` authorization header value for `username` and `password`.
+pub fn basic_auth(username: &str, password: Option<&str>) -> HeaderValue {
+    let credentials = format!("{}:{}", username, password.unwrap_or_default());
+    let mut value = HeaderValue::try_from(format!("Basic {}", STANDARD.encode(credentials)))
+        .expect("base64 is a valid header value");
+    value.set_sensitive(true);
+    value
+}
+
+/// An http proxy that raw tcp connections (websockets, Electrum) are tunneled
+/// through with `CONNECT`.
+#[derive(Clone)]
+pub struct TcpProxy {
+    address: String,
+    authorization: Option<HeaderValue>,
+}
+
+impl TcpProxy {
+    /// The proxy of `config`, if any. Only `http://` proxies can tunnel.
+    pub fn from_config(config: &HttpConfig) -> AdapterResult<Option<Self>> {
+        let Some(proxy) = &config.proxy else {
+            return Ok(None);
+        };
+        // the proxy url may hold credentials, so it's left out of the errors
+        let url = reqwest::Url::parse(proxy).map_err(|_| "Invalid proxy url")?;
+        if url.scheme() != "http" {
+            return Err("Only http:// proxies can tunnel websocket and Electrum rpcs".into());
+        }
+        let host = url.host_str().ok_or("Invalid proxy url")?;
+        let authorization =
+            (!url.username().is_empty()).then(|| basic_auth(url.username(), url.password()));
+        Ok(Some(TcpProxy {
+            address: format!("{}:{}", host, url.port().unwrap_or(80)),
+            authorization,
+        }))
+    }
+
+    async fn tunnel(&self, host: &str, port: u16) -> AdapterResult<TcpStream> {
+        let mut stream = TcpStream::connect(&self.address).await?;
+        let mut request = format!("CONNECT {0}:{1} HTTP/1.1\r\nHost: {0}:{1}\r\n", host, port);
+        if let Some(authorization) = &self.authorization {
+            request.push_str("Proxy-Authorization: ");
+            request.push_str(authorization.to_str()?);
+            request.push_str("\r\n");
+        }
+        request.push_str("\r\n");
+        stream.write_all(request.as_bytes()).await?;
+
+        // read the response a byte at a time so nothing after it is consumed
+        let mut response = Vec::new();
+        while !response.ends_with(b"\r\n\r\n") {
+            if response.len() > MAX_PROXY_RESPONSE {
+                return Err("Invalid proxy response".into());
+            }
+            let mut byte = [0];
+            if stream.read(&mut byte).await? == 0 {
+                return Err("Proxy closed the connection".into());
+            }
+            response.push(byte[0]);
+        }
+        let status = String::from_utf8_lossy(&response);
+        match status.split_whitespace().nth(1) {
+            Some("200") => Ok(stream),
+            status => Err(format!("Proxy refused to tunnel: {}", status.unwrap_or("?")).into()),
+        }
+    }
+}
+
+/// Opens a tcp connection to `host:port`, through `proxy` when there is one.
+pub async fn connect_tcp(
+    host: &str,
+    port: u16,
+    proxy: Option<&TcpProxy>,
+) -> AdapterResult<TcpStream> {
+    match proxy {
+        Some(proxy) => proxy.tunnel(host, port).await,
+        None => Ok(TcpStream::connect((host, port)).await?),
+    }
+}
+
+/// `url` as reqwest sends it, so that differently written urls of an rpc still match.
+fn normalize(url: &str) -> String {
+    reqwest::Url::parse(url).map_or_else(|_| url.to_string(), String::from)
+}
+
+fn rpc_base(url: &str) -> String {
+    let url = normalize(url);
+    let base = url.split_once('?').map_or(url.as_str(), |(base, _)| base);
+    base.trim_end_matches('/').to_string()
+}
+
+/// A request to an rpc, sent with that rpc's retry policy.
+pub struct RpcRequest {
+    builder: reqwest::RequestBuilder,
+    retry: RetryPolicy,
+}
+
+impl RpcRequest {
+    pub fn json<T: Serialize + ?Sized>(self, body: &T) -> Self {
+        RpcRequest {
+            builder: self.builder.json(body),
+            ..self
+        }
+    }
+
+    /// Sends the request, retrying transient failures with exponential backoff.
+    /// Other error statuses are returned as responses, since JSON-RPC servers such as
+    /// bitcoind send their errors in the body of a 500.
+    pub async fn send(self) -> reqwest::Result<Response> {
+        let mut builder = self.builder;
+        let mut backoff = Duration::from_millis(self.retry.backoff_ms);
+        let mut attempt = 1;
+        loop {
+            let retry = if attempt < self.retry.attempts {
+                builder.try_clone()
+            } else {
+                None
+            };
+            let result = builder.send().await;
+            match (result, retry) {
+                (result, Some(retry)) if is_transient(&result) => {
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                    attempt += 1;
+                    builder = retry;
+                }
+                (result, _) => return result,
+            }
+        }
+    }
+}
+
+fn is_transient(result: &reqwest::Result<Response>) -> bool {
+    match result {
+        Ok(response) => matches!(
+            response.status(),
+            StatusCode::TOO_MANY_REQUESTS
+                | StatusCode::BAD_GATEWAY
+                | StatusCode::SERVICE_UNAVAILABLE
+                | StatusCode::GATEWAY_TIMEOUT
+        ),
+        Err(e) => e.is_timeout() || e.is_connect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use axum::{http::HeaderMap, routing::get, Router};
+
+    use super::*;
+    use crate::{chains::mock_server::serve, models::assets::RpcBackend};
+
+    fn endpoint(url: String, retry: RetryPolicy) -> RpcEndpoint {
+        RpcEndpoint {
+            url,
+            backend: RpcBackend::default(),
+            username: None,
+            password: None,
+            headers: [("x-api-key".to_string(), "secret".to_string())].into(),
+            retry,
+            ws: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors_with_rpc_headers() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let counter = calls.clone();
+        let base = serve(Router::new().route(
+            "/api/blocks/tip/height",
+            get(move |headers: HeaderMap| async move {
+                assert_eq!(headers["x-api-key"], "secret");
+                match counter.fetch_add(1, Ordering::SeqCst) {
+                    0 => Err(StatusCode::BAD_GATEWAY),
+                    _ => Ok("840000"),
+                }
+            }),
+        ))
+        .await;
+        let retry = RetryPolicy {
+            attempts: 3,
+            backoff_ms: 1,
+        };
+        let client = HttpClient::new(
+            &HttpConfig::default(),
+            &[endpoint(format!("{}/api/", base), retry)],
+        )
+        .unwrap();
+        let response = client
+            .get(format!("{}/api/blocks/tip/height", base))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.text().await.unwrap(), "840000");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_client_errors() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let counter = calls.clone();
+        let base = serve(Router::new().route(
+            "/",
+            get(move || async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                StatusCode::UNAUTHORIZED
+            }),
+        ))
+        .await;
+        let retry = RetryPolicy {
+            attempts: 3,
+            backoff_ms: 1,
+        };
+        let client =
+            HttpClient::new(&HttpConfig::default(), &[endpoint(base.clone(), retry)]).unwrap();
+        let response = client.get(&base).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,10 @@
+use axum::Router;
+use tokio::net::TcpListener;
+
+/// Serves `router` on a random local port and returns its base url.
+pub async fn serve(router: Router) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+    format!("http://{}", address)
+}
@@ -0,0 +1,209 @@
+use std::{collections::HashSet, sync::Mutex};
+
+use async_trait::async_trait;
+use serde_json::json;
+
+use super::{
+    evm::{
+        evm_block_tag, fetch_evm_balances, fetch_evm_block_number, fetch_evm_header,
+        new_heads_subscription,
+    },
+    http::HttpClient,
+    json_rpc, json_rpc_batch, parse_hex_u64, AdapterResult, BlockHeader, BlockTag, ChainAdapter,
+    JsonRpcError, LayerHeights, TipSubscription,
+};
+use crate::models::{assets::HeightKind, balances::BalanceQuery};
+
+/// The `L1Block` predeploy, which holds the L1 origin of the current L2 block.
+const L1_BLOCK_PREDEPLOY: &str = "0x4200000000000000000000000000000000000015";
+/// `number()` selector.
+const L1_BLOCK_NUMBER_CALLDATA: &str = "0x8381f58a";
+
+/// OP Stack chains (Optimism, Base, ...) publish their own L2 height by default. The
+/// L1 origin comes from `optimism_syncStatus` when the rpc is also a rollup node, and
+/// from the `L1Block` predeploy otherwise.
+pub struct OpStackAdapter {
+    client: HttpClient,
+    primary: HeightKind,
+    /// Rpcs that don't implement `optimism_syncStatus`, so it isn't retried on them.
+    no_sync_status: Mutex<HashSet<String>>,
+}
+
+impl OpStackAdapter {
+    pub fn new(client: HttpClient, primary_height: Option<HeightKind>) -> Self {
+        OpStackAdapter {
+            client,
+            primary: primary_height.unwrap_or(HeightKind::L2),
+            no_sync_status: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Returns the number of the L2 block identified by `tag` and of its L1 origin.
+    async fn fetch_with_l1_origin(&self, rpc: &str, tag: BlockTag) -> AdapterResult<(u64, u64)> {
+        if !self.no_sync_status.lock().unwrap().contains(rpc) {
+            match json_rpc(&self.client, rpc, "optimism_syncStatus", json!([])).await {
+                Ok(status) => {
+                    let block = &status[sync_status_field(tag)];
+                    let number = block["number"].as_u64().ok_or("Missing L2 block number")?;
+                    let origin = block["l1origin"]["number"]
+                        .as_u64()
+                        .ok_or("Missing L1 origin")?;
+                    return Ok((number, origin));
+                }
+                // only a missing method is permanent; other errors may be transient
+                Err(e) if JsonRpcError::is_method_not_found(e.as_ref()) => {
+                    self.no_sync_status.lock().unwrap().insert(rpc.to_string());
+                }
+                Err(_) => {}
+            }
+        }
+
+        let block_tag = evm_block_tag(tag);
+        let results = json_rpc_batch(
+            &self.client,
+            rpc,
+            &[
+                ("eth_getBlockByNumber", json!([block_tag, false])),
+                (
+                    "eth_call",
+                    json!([{ "to": L1_BLOCK_PREDEPLOY, "data": L1_BLOCK_NUMBER_CALLDATA }, block_tag]),
+                ),
+            ],
+        )
+        .await?;
+        let number = parse_hex_u64(&results[0]["number"])?;
+        let origin = parse_hex_u64(&results[1])?;
+        Ok((number, origin))
+    }
+}
+
+/// The `optimism_syncStatus` field holding the L2 block at `tag`.
+fn sync_status_field(tag: BlockTag) -> &'static str {
+    match tag {
+        BlockTag::LATEST => "unsafe_l2",
+        BlockTag::SAFE => "safe_l2",
+        BlockTag::FINALIZED => "finalized_l2",
+    }
+}
+
+#[async_trait]
+impl ChainAdapter for OpStackAdapter {
+    async fn fetch_tip(&self, rpc: &str) -> AdapterResult<u64> {
+        self.fetch_by_tag(rpc, BlockTag::LATEST).await
+    }
+
+    async fn fetch_by_tag(&self, rpc: &str, tag: BlockTag) -> AdapterResult<u64> {
+        match self.primary {
+            HeightKind::L2 => fetch_evm_block_number(&self.client, rpc, tag).await,
+            HeightKind::L1 => Ok(self.fetch_with_l1_origin(rpc, tag).await?.1),
+        }
+    }
+
+    fn is_layer2(&self) -> bool {
+        true
+    }
+
+    async fn fetch_layer_heights(&self, rpc: &str) -> AdapterResult<LayerHeights> {
+        let (l2, l1) = self.fetch_with_l1_origin(rpc, BlockTag::LATEST).await?;
+        Ok(LayerHeights {
+            l1: Some(l1),
+            l1_batch: None,
+            l2,
+        })
+    }
+
+    fn tip_subscription(&self) -> Option<TipSubscription> {
+        (self.primary == HeightKind::L2).then(new_heads_subscription)
+    }
+
+    fn supports_headers(&self) -> bool {
+        self.primary == HeightKind::L2
+    }
+
+    async fn fetch_header(&self, rpc: &str, height: u64) -> AdapterResult<BlockHeader> {
+        fetch_evm_header(&self.client, rpc, height).await
+    }
+
+    fn supports_balances(&self) -> bool {
+        true
+    }
+
+    async fn fetch_balances(
+        &self,
+        rpc: &str,
+        queries: &[BalanceQuery],
+    ) -> AdapterResult<Vec<Option<u128>>> {
+        fetch_evm_balances(&self.client, rpc, queries).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    };
+
+    use axum::{routing::post, Json, Router};
+    use serde_json::Value;
+
+    use super::*;
+    use crate::chains::mock_server::serve;
+
+    /// Serves an op-node whose `optimism_syncStatus` fails with `code` on its first call.
+    async fn serve_op_rpc(code: i64) -> String {
+        let calls = Arc::new(AtomicU32::new(0));
+        let answer = move |call: &Value| {
+            let result = match call["method"].as_str() {
+                Some("optimism_syncStatus") if calls.fetch_add(1, Ordering::SeqCst) == 0 => {
+                    let error = json!({ "code": code, "message": "unavailable" });
+                    return json!({ "jsonrpc": "2.0", "id": call["id"], "error": error });
+                }
+                Some("optimism_syncStatus") => json!({
+                    "unsafe_l2": { "number": 200, "l1origin": { "number": 20 } }
+                }),
+                Some("eth_getBlockByNumber") => json!({ "number": "0x64" }),
+                _ => json!("0x0a"),
+            };
+            json!({ "jsonrpc": "2.0", "id": call["id"], "result": result })
+        };
+        serve(Router::new().route(
+            "/",
+            post(move |Json(body): Json<Value>| async move {
+                Json(match &body {
+                    Value::Array(calls) => Value::Array(calls.iter().map(&answer).collect()),
+                    call => answer(call),
+                })
+            }),
+        ))
+        .await
+    }
+
+    #[tokio::test]
+    async fn retries_sync_status_after_transient_errors() {
+        let rpc = serve_op_rpc(-32000).await;
+        let adapter = OpStackAdapter::new(HttpClient::default(), None);
+        assert_eq!(
+            adapter.fetch_layer_heights(&rpc).await.unwrap().l1,
+            Some(10)
+        );
+        assert_eq!(
+            adapter.fetch_layer_heights(&rpc).await.unwrap().l1,
+            Some(20)
+        );
+    }
+
+    #[tokio::test]
+    async fn stops_asking_rpcs_without_sync_status() {
+        let rpc = serve_op_rpc(-32601).await;
+        let adapter = OpStackAdapter::new(HttpClient::default(), None);
+        assert_eq!(
+            adapter.fetch_layer_heights(&rpc).await.unwrap().l1,
+            Some(10)
+        );
+        assert_eq!(
+            adapter.fetch_layer_heights(&rpc).await.unwrap().l1,
+            Some(10)
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+use super::{
+    evm::{fetch_evm_balances, fetch_evm_block_number, fetch_evm_header, new_heads_subscription},
+    http::HttpClient,
+    json_rpc, json_rpc_batch, parse_hex_u64, parse_u64, AdapterResult, BlockHeader, BlockTag,
+    ChainAdapter, LayerHeights, TipSubscription,
+};
+use crate::models::{assets::HeightKind, balances::BalanceQuery};
+
+/// Scroll chains publish their own L2 height by default. The L1 height is the latest
+/// L1 block the node has synced bridge messages from, as reported by
+/// `scroll_syncStatus`.
+pub struct ScrollAdapter {
+    client: HttpClient,
+    primary: HeightKind,
+}
+
+impl ScrollAdapter {
+    pub fn new(client: HttpClient, primary_height: Option<HeightKind>) -> Self {
+        ScrollAdapter {
+            client,
+            primary: primary_height.unwrap_or(HeightKind::L2),
+        }
+    }
+}
+
+/// The L1 height from a `scroll_syncStatus` result.
+fn l1_sync_height(status: &Value) -> AdapterResult<u64> {
+    parse_u64(&status["l1MessageSyncHeight"]).map_err(|_| "Missing l1MessageSyncHeight".into())
+}
+
+#[async_trait]
+impl ChainAdapter for ScrollAdapter {
+    async fn fetch_tip(&self, rpc: &str) -> AdapterResult<u64> {
+        self.fetch_by_tag(rpc, BlockTag::LATEST).await
+    }
+
+    async fn fetch_by_tag(&self, rpc: &str, tag: BlockTag) -> AdapterResult<u64> {
+        match (self.primary, tag) {
+            (HeightKind::L2, _) => fetch_evm_block_number(&self.client, rpc, tag).await,
+            (HeightKind::L1, BlockTag::LATEST) => {
+                l1_sync_height(&json_rpc(&self.client, rpc, "scroll_syncStatus", json!([])).await?)
+            }
+            // the sync status has no L1 heights for the safe or finalized L2 blocks
+            (HeightKind::L1, _) => {
+                Err("Scroll rpcs only report the L1 height of their latest block".into())
+            }
+        }
+    }
+
+    fn is_layer2(&self) -> bool {
+        true
+    }
+
+    async fn fetch_layer_heights(&self, rpc: &str) -> AdapterResult<LayerHeights> {
+        let results = json_rpc_batch(
+            &self.client,
+            rpc,
+            &[
+                ("eth_blockNumber", json!([])),
+                ("scroll_syncStatus", json!([])),
+            ],
+        )
+        .await?;
+        Ok(LayerHeights {
+            l1: Some(l1_sync_height(&results[1])?),
+            l1_batch: None,
+            l2: parse_hex_u64(&results[0])?,
+        })
+    }
+
+    fn tip_subscription(&self) -> Option<TipSubscription> {
+        (self.primary == HeightKind::L2).then(new_heads_subscription)
+    }
+
+    fn supports_headers(&self) -> bool {
+        self.primary == HeightKind::L2
+    }
+
+    async fn fetch_header(&self, rpc: &str, height: u64) -> AdapterResult<BlockHeader> {
+        fetch_evm_header(&self.client, rpc, height).await
+    }
+
+    fn supports_balances(&self) -> bool {
+        true
+    }
+
+    async fn fetch_balances(
+        &self,
+        rpc: &str,
+        queries: &[BalanceQuery],
+    ) -> AdapterResult<Vec<Option<u128>>> {
+        fetch_evm_balances(&self.client, rpc, queries).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{routing::post, Json, Router};
+
+    use super::*;
+    use crate::chains::mock_server::serve;
+
+    async fn serve_scroll_rpc() -> String {
+        let answer = |call: &Value| {
+            let result = match call["method"].as_str() {
+                Some("eth_blockNumber") => json!("0x7a120"),
+                Some("eth_getBlockByNumber") => json!({ "number": "0x7a120" }),
+                Some("scroll_syncStatus") => json!({
+                    "l1MessageSyncHeight": 21000000,
+                    "l1RollupSyncHeight": 20999990,
+                    "l2FinalizedBlockHeight": 499000
+                }),
+                _ => {
+                    return json!({ "jsonrpc": "2.0", "id": call["id"], "error": { "code": -32601, "message": "method not found" } })
+                }
+            };
+            json!({ "jsonrpc": "2.0", "id": call["id"], "result": result })
+        };
+        serve(Router::new().route(
+            "/",
+            post(move |Json(body): Json<Value>| async move {
+                Json(match &body {
+                    Value::Array(calls) => Value::Array(calls.iter().map(answer).collect()),
+                    call => answer(call),
+                })
+            }),
+        ))
+        .await
+    }
+
+    #[tokio::test]
+    async fn reports_both_layer_heights() {
+        let rpc = serve_scroll_rpc().await;
+        let adapter = ScrollAdapter::new(HttpClient::default(), None);
+        let heights = adapter.fetch_layer_heights(&rpc).await.unwrap();
+        assert_eq!(heights.l2, 500000);
+        assert_eq!(heights.l1, Some(21000000));
+        assert_eq!(adapter.fetch_tip(&rpc).await.unwrap(), 500000);
+    }
+
+    #[tokio::test]
+    async fn publishes_the_l1_height_when_primary() {
+        let rpc = serve_scroll_rpc().await;
+        let adapter = ScrollAdapter::new(HttpClient::default(), Some(HeightKind::L1));
+        assert_eq!(adapter.fetch_tip(&rpc).await.unwrap(), 21000000);
+        assert!(adapter
+            .fetch_by_tag(&rpc, BlockTag::FINALIZED)
+            .await
+            .is_err());
+        assert!(adapter.tip_subscription().is_none());
+    }
+}
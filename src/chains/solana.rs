@@ -0,0 +1,196 @@
+use async_trait::async_trait;
+use serde_json::json;
+
+use super::{
+    http::HttpClient, json_rpc, AdapterResult, BlockHeader, BlockTag, ChainAdapter, SlotHeights,
+    TipSubscription,
+};
+use crate::models::assets::{SolanaCommitment, SolanaHeight};
+
+/// Publishes either the slot or the block height of a solana chain, reading the
+/// latest one at the configured commitment. Both are reported in the detailed
+/// response.
+pub struct SolanaAdapter {
+    client: HttpClient,
+    height: SolanaHeight,
+    commitment: SolanaCommitment,
+}
+
+impl SolanaAdapter {
+    pub fn new(client: HttpClient, height: SolanaHeight, commitment: SolanaCommitment) -> Self {
+        SolanaAdapter {
+            client,
+            height,
+            commitment,
+        }
+    }
+
+    /// Reads the slot or block height at the commitment of `tag`.
+    async fn fetch_height(
+        &self,
+        rpc: &str,
+        height: SolanaHeight,
+        tag: BlockTag,
+    ) -> AdapterResult<u64> {
+        let method = match height {
+            SolanaHeight::SLOT => "getSlot",
+            SolanaHeight::BLOCKHEIGHT => "getBlockHeight",
+        };
+        let result = json_rpc(
+            &self.client,
+            rpc,
+            method,
+            json!([{ "commitment": self.commitment(tag) }]),
+        )
+        .await?;
+
+        Ok(result
+            .as_u64()
+            .ok_or(format!("Invalid {} response", method))?)
+    }
+
+    fn commitment(&self, tag: BlockTag) -> &'static str {
+        match tag {
+            BlockTag::LATEST => self.commitment.as_str(),
+            BlockTag::SAFE => "confirmed",
+            BlockTag::FINALIZED => "finalized",
+        }
+    }
+}
+
+#[async_trait]
+impl ChainAdapter for SolanaAdapter {
+    async fn fetch_tip(&self, rpc: &str) -> AdapterResult<u64> {
+        self.fetch_by_tag(rpc, BlockTag::LATEST).await
+    }
+
+    async fn fetch_by_tag(&self, rpc: &str, tag: BlockTag) -> AdapterResult<u64> {
+        self.fetch_height(rpc, self.height, tag).await
+    }
+
+    // slot notifications are sent as soon as a slot is processed, so they only match
+    // the polled height at `processed` commitment
+    fn tip_subscription(&self) -> Option<TipSubscription> {
+        let pushes_tip =
+            self.height == SolanaHeight::SLOT && self.commitment == SolanaCommitment::PROCESSED;
+        pushes_tip.then(|| TipSubscription {
+            method: "slotSubscribe",
+            params: json!([]),
+            parse: |slot| Ok(slot["slot"].as_u64().ok_or("Invalid slot notification")?),
+        })
+    }
+
+    fn supports_slot_heights(&self) -> bool {
+        true
+    }
+
+    async fn fetch_slot_heights(&self, rpc: &str, tip: u64) -> AdapterResult<SlotHeights> {
+        let other = match self.height {
+            SolanaHeight::SLOT => SolanaHeight::BLOCKHEIGHT,
+            SolanaHeight::BLOCKHEIGHT => SolanaHeight::SLOT,
+        };
+        let height = self.fetch_height(rpc, other, BlockTag::LATEST).await?;
+        Ok(match self.height {
+            SolanaHeight::SLOT => SlotHeights {
+                slot: tip,
+                block_height: height,
+            },
+            SolanaHeight::BLOCKHEIGHT => SlotHeights {
+                slot: height,
+                block_height: tip,
+            },
+        })
+    }
+
+    // blocks are only addressable by slot, so headers can't be looked up by block
+    // height, and `getBlock` only serves confirmed blocks, which processed slots may
+    // not be yet
+    fn supports_headers(&self) -> bool {
+        self.height == SolanaHeight::SLOT && self.commitment != SolanaCommitment::PROCESSED
+    }
+
+    async fn fetch_header(&self, rpc: &str, height: u64) -> AdapterResult<BlockHeader> {
+        let block = json_rpc(
+            &self.client,
+            rpc,
+            "getBlock",
+            json!([
+                height,
+                {
+                    "commitment": self.commitment.as_str(),
+                    "transactionDetails": "none",
+                    "rewards": false,
+                    "maxSupportedTransactionVersion": 0
+                }
+            ]),
+        )
+        .await?;
+        Ok(BlockHeader {
+            number: height,
+            hash: block["blockhash"]
+                .as_str()
+                .ok_or("Missing blockhash")?
+                .to_string(),
+            parent_hash: block["previousBlockhash"]
+                .as_str()
+                .ok_or("Missing previousBlockhash")?
+                .to_string(),
+            timestamp: block["blockTime"].as_u64().ok_or("Missing blockTime")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{routing::post, Json, Router};
+    use serde_json::Value;
+
+    use super::*;
+    use crate::chains::mock_server::serve;
+
+    /// A solana rpc whose `getBlock` only answers at `finalized` commitment.
+    async fn serve_solana_rpc() -> String {
+        serve(Router::new().route(
+            "/",
+            post(|Json(call): Json<Value>| async move {
+                assert_eq!(call["method"], "getBlock");
+                assert_eq!(call["params"][1]["commitment"], "finalized");
+                Json(json!({
+                    "jsonrpc": "2.0",
+                    "id": call["id"],
+                    "result": {
+                        "blockhash": "5Z7ZQfu1Jm5gTqP2oqCR5t4Xo1uQNm9dUqFLiAXmyp1Y",
+                        "previousBlockhash": "9RpFfzyGvHByVBVbvWcuXDGMDWJ3Bpn4zePH5Wcq4qfN",
+                        "blockTime": 1700000000
+                    }
+                }))
+            }),
+        ))
+        .await
+    }
+
+    #[tokio::test]
+    async fn reads_headers_at_the_configured_commitment() {
+        let rpc = serve_solana_rpc().await;
+        let adapter = SolanaAdapter::new(
+            HttpClient::default(),
+            SolanaHeight::SLOT,
+            SolanaCommitment::FINALIZED,
+        );
+        assert!(adapter.supports_headers());
+        let header = adapter.fetch_header(&rpc, 250_000_000).await.unwrap();
+        assert_eq!(header.number, 250_000_000);
+        assert_eq!(header.timestamp, 1700000000);
+    }
+
+    #[test]
+    fn skips_headers_of_processed_slots() {
+        let adapter = SolanaAdapter::new(
+            HttpClient::default(),
+            SolanaHeight::SLOT,
+            SolanaCommitment::PROCESSED,
+        );
+        assert!(!adapter.supports_headers());
+        assert!(adapter.tip_subscription().is_some());
+    }
+}
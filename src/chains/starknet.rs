@@ -0,0 +1,170 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use async_trait::async_trait;
+use serde_json::json;
+
+use super::{
+    http::HttpClient, json_rpc, json_rpc_batch, AdapterResult, BlockHeader, BlockTag, ChainAdapter,
+    TipSubscription,
+};
+
+/// Publishes the latest block accepted on L2. Its finalized height is the latest
+/// block accepted on L1, which is what settlement depends on.
+pub struct StarknetAdapter {
+    client: HttpClient,
+    /// Highest block last seen accepted on L1, used as the lower bound of the next
+    /// search since L1 acceptance only moves forward.
+    l1_accepted_hint: AtomicU64,
+}
+
+impl StarknetAdapter {
+    pub fn new(client: HttpClient) -> Self {
+        StarknetAdapter {
+            client,
+            l1_accepted_hint: AtomicU64::new(0),
+        }
+    }
+
+    async fn is_accepted_on_l1(&self, rpc: &str, block_number: u64) -> AdapterResult<bool> {
+        let block = json_rpc(
+            &self.client,
+            rpc,
+            "starknet_getBlockWithTxHashes",
+            json!([{ "block_number": block_number }]),
+        )
+        .await?;
+        let status = block["status"].as_str().ok_or("Missing block status")?;
+        Ok(status == "ACCEPTED_ON_L1")
+    }
+
+    /// Binary searches for the highest block whose status is `ACCEPTED_ON_L1`.
+    async fn fetch_l1_accepted(&self, rpc: &str) -> AdapterResult<u64> {
+        let tip = self.fetch_tip(rpc).await?;
+        if self.is_accepted_on_l1(rpc, tip).await? {
+            return Ok(tip);
+        }
+
+        let mut low = self.l1_accepted_hint.load(Ordering::Relaxed).min(tip);
+        if !self.is_accepted_on_l1(rpc, low).await? {
+            if low == 0 {
+                return Err("No block accepted on L1 yet".into());
+            }
+            low = 0;
+            if !self.is_accepted_on_l1(rpc, low).await? {
+                return Err("No block accepted on L1 yet".into());
+            }
+        }
+
+        // invariant: `low` is accepted on L1 and `high` is not
+        let mut high = tip;
+        while high - low > 1 {
+            let mid = low + (high - low) / 2;
+            if self.is_accepted_on_l1(rpc, mid).await? {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+
+        self.l1_accepted_hint.fetch_max(low, Ordering::Relaxed);
+        Ok(low)
+    }
+}
+
+#[async_trait]
+impl ChainAdapter for StarknetAdapter {
+    async fn fetch_tip(&self, rpc: &str) -> AdapterResult<u64> {
+        let result = json_rpc(&self.client, rpc, "starknet_blockNumber", json!([])).await?;
+
+        match result {
+            serde_json::Value::Number(n) => Ok(n.as_u64().ok_or("Invalid number")?),
+            _ => Err("Unexpected starknet block number format".into()),
+        }
+    }
+
+    async fn fetch_by_tag(&self, rpc: &str, tag: BlockTag) -> AdapterResult<u64> {
+        match tag {
+            // blocks accepted on L2 are only reverted if the sequencer misbehaves
+            BlockTag::LATEST | BlockTag::SAFE => self.fetch_tip(rpc).await,
+            BlockTag::FINALIZED => self.fetch_l1_accepted(rpc).await,
+        }
+    }
+
+    fn tip_subscription(&self) -> Option<TipSubscription> {
+        Some(TipSubscription {
+            method: "starknet_subscribeNewHeads",
+            params: json!({}),
+            parse: |head| Ok(head["block_number"].as_u64().ok_or("Invalid new head")?),
+        })
+    }
+
+    fn supports_pending(&self) -> bool {
+        true
+    }
+
+    async fn fetch_pending(&self, rpc: &str) -> AdapterResult<u64> {
+        let results = json_rpc_batch(
+            &self.client,
+            rpc,
+            &[
+                ("starknet_getBlockWithTxHashes", json!(["pending"])),
+                ("starknet_blockHashAndNumber", json!([])),
+            ],
+        )
+        .await?;
+        let (pending, latest) = (&results[0], &results[1]);
+        // pending blocks have no number of their own, only the hash of their parent
+        let parent_hash = pending["parent_hash"]
+            .as_str()
+            .ok_or("Missing pending parent hash")?;
+        if latest["block_hash"].as_str() == Some(parent_hash) {
+            let latest = latest["block_number"]
+                .as_u64()
+                .ok_or("Missing block number")?;
+            return Ok(latest + 1);
+        }
+
+        // the latest block moved between the two calls, so look the parent up
+        let parent = json_rpc(
+            &self.client,
+            rpc,
+            "starknet_getBlockWithTxHashes",
+            json!([{ "block_hash": parent_hash }]),
+        )
+        .await?;
+        let parent = parent["block_number"]
+            .as_u64()
+            .ok_or("Missing block number")?;
+        Ok(parent + 1)
+    }
+
+    fn supports_headers(&self) -> bool {
+        true
+    }
+
+    async fn fetch_header(&self, rpc: &str, height: u64) -> AdapterResult<BlockHeader> {
+        let block = json_rpc(
+            &self.client,
+            rpc,
+            "starknet_getBlockWithTxHashes",
+            json!([{ "block_number": height }]),
+        )
+        .await?;
+        Ok(BlockHeader {
+            number: block["block_number"]
+                .as_u64()
+                .ok_or("Missing block number")?,
+            hash: block["block_hash"]
+                .as_str()
+                .ok_or("Missing block hash")?
+                .to_string(),
+            parent_hash: block["parent_hash"]
+                .as_str()
+                .ok_or("Missing parent hash")?
+                .to_string(),
+            timestamp: block["timestamp"]
+                .as_u64()
+                .ok_or("Missing block timestamp")?,
+        })
+    }
+}
@@ -0,0 +1,75 @@
+use async_trait::async_trait;
+use serde_json::json;
+
+use super::{http::HttpClient, json_rpc, parse_u64, AdapterResult, BlockTag, ChainAdapter};
+
+/// Sui reports the sequence number of its latest checkpoint. Checkpoints are final
+/// once certified, so every tag resolves to the same height.
+pub struct SuiAdapter {
+    client: HttpClient,
+}
+
+impl SuiAdapter {
+    pub fn new(client: HttpClient) -> Self {
+        SuiAdapter { client }
+    }
+}
+
+#[async_trait]
+impl ChainAdapter for SuiAdapter {
+    async fn fetch_tip(&self, rpc: &str) -> AdapterResult<u64> {
+        let result = json_rpc(
+            &self.client,
+            rpc,
+            "sui_getLatestCheckpointSequenceNumber",
+            json!([]),
+        )
+        .await?;
+        parse_u64(&result)
+    }
+
+    async fn fetch_by_tag(&self, rpc: &str, _tag: BlockTag) -> AdapterResult<u64> {
+        self.fetch_tip(rpc).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{routing::post, Json, Router};
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::chains::mock_server::serve;
+
+    async fn mock_rpc(result: Value) -> String {
+        serve(Router::new().route(
+            "/",
+            post(move |Json(request): Json<Value>| async move {
+                assert_eq!(request["method"], "sui_getLatestCheckpointSequenceNumber");
+                Json(json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }))
+            }),
+        ))
+        .await
+    }
+
+    #[tokio::test]
+    async fn fetches_latest_checkpoint() {
+        let rpc = mock_rpc(json!("98231234")).await;
+        let adapter = SuiAdapter::new(HttpClient::default());
+        assert_eq!(adapter.fetch_tip(&rpc).await.unwrap(), 98231234);
+        assert_eq!(
+            adapter
+                .fetch_by_tag(&rpc, BlockTag::FINALIZED)
+                .await
+                .unwrap(),
+            98231234
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_checkpoint() {
+        let rpc = mock_rpc(json!(null)).await;
+        let adapter = SuiAdapter::new(HttpClient::default());
+        assert!(adapter.fetch_tip(&rpc).await.is_err());
+    }
+}
@@ -0,0 +1,77 @@
+use async_trait::async_trait;
+use serde_json::Value;
+
+use super::{http::HttpClient, rest_url, send_json, AdapterResult, BlockTag, ChainAdapter};
+
+/// TON reports the seqno of the last masterchain block from a toncenter v2 api (e.g.
+/// `https://toncenter.com/api/v2`). Masterchain blocks are final, so every tag
+/// resolves to the same height.
+pub struct TonAdapter {
+    client: HttpClient,
+}
+
+impl TonAdapter {
+    pub fn new(client: HttpClient) -> Self {
+        TonAdapter { client }
+    }
+}
+
+#[async_trait]
+impl ChainAdapter for TonAdapter {
+    async fn fetch_tip(&self, rpc: &str) -> AdapterResult<u64> {
+        let response: Value =
+            send_json(self.client.get(rest_url(rpc, "getMasterchainInfo")?)).await?;
+        if response["ok"] != true {
+            return Err(format!("getMasterchainInfo failed: {}", response["error"]).into());
+        }
+        Ok(response["result"]["last"]["seqno"]
+            .as_u64()
+            .ok_or("Missing masterchain seqno")?)
+    }
+
+    async fn fetch_by_tag(&self, rpc: &str, _tag: BlockTag) -> AdapterResult<u64> {
+        self.fetch_tip(rpc).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{extract::Query, routing::get, Json, Router};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::chains::mock_server::serve;
+
+    #[tokio::test]
+    async fn fetches_masterchain_seqno_keeping_api_key() {
+        let base = serve(Router::new().route(
+            "/api/v2/getMasterchainInfo",
+            get(|Query(query): Query<HashMap<String, String>>| async move {
+                assert_eq!(query.get("api_key").map(String::as_str), Some("secret"));
+                Json(json!({
+                    "ok": true,
+                    "result": {
+                        "@type": "blocks.masterchainInfo",
+                        "last": { "workchain": -1, "seqno": 44810233 }
+                    }
+                }))
+            }),
+        ))
+        .await;
+        let adapter = TonAdapter::new(HttpClient::default());
+        let rpc = format!("{}/api/v2/?api_key=secret", base);
+        assert_eq!(adapter.fetch_tip(&rpc).await.unwrap(), 44810233);
+    }
+
+    #[tokio::test]
+    async fn fails_when_not_ok() {
+        let base = serve(Router::new().route(
+            "/getMasterchainInfo",
+            get(|| async { Json(json!({ "ok": false, "error": "rate limited" })) }),
+        ))
+        .await;
+        let adapter = TonAdapter::new(HttpClient::default());
+        assert!(adapter.fetch_tip(&base).await.is_err());
+    }
+}
@@ -0,0 +1,90 @@
+use async_trait::async_trait;
+use serde_json::Value;
+
+use super::{http::HttpClient, rest_url, send_json, AdapterResult, BlockTag, ChainAdapter};
+
+/// Tron reports the number of its latest block from the full node's `wallet` api.
+/// Safe and finalized heights come from the solidity node's `walletsolidity` api,
+/// which only serves solidified blocks.
+pub struct TronAdapter {
+    client: HttpClient,
+}
+
+impl TronAdapter {
+    pub fn new(client: HttpClient) -> Self {
+        TronAdapter { client }
+    }
+
+    async fn fetch_block_number(&self, rpc: &str, path: &str) -> AdapterResult<u64> {
+        let block: Value = send_json(self.client.post(rest_url(rpc, path)?)).await?;
+        Ok(block["block_header"]["raw_data"]["number"]
+            .as_u64()
+            .ok_or("Missing block number")?)
+    }
+}
+
+#[async_trait]
+impl ChainAdapter for TronAdapter {
+    async fn fetch_tip(&self, rpc: &str) -> AdapterResult<u64> {
+        self.fetch_by_tag(rpc, BlockTag::LATEST).await
+    }
+
+    async fn fetch_by_tag(&self, rpc: &str, tag: BlockTag) -> AdapterResult<u64> {
+        match tag {
+            BlockTag::LATEST => self.fetch_block_number(rpc, "wallet/getnowblock").await,
+            BlockTag::SAFE | BlockTag::FINALIZED => {
+                self.fetch_block_number(rpc, "walletsolidity/getnowblock")
+                    .await
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{routing::post, Json, Router};
+    use serde_json::json;
+
+    use super::*;
+    use crate::chains::mock_server::serve;
+
+    fn block(number: u64) -> Json<Value> {
+        Json(json!({
+            "blockID": "0000000003d4ab55",
+            "block_header": {
+                "raw_data": { "number": number, "timestamp": 1735689600000u64 }
+            }
+        }))
+    }
+
+    #[tokio::test]
+    async fn fetches_latest_and_solidified_blocks() {
+        let rpc = serve(
+            Router::new()
+                .route("/wallet/getnowblock", post(|| async { block(64269141) }))
+                .route(
+                    "/walletsolidity/getnowblock",
+                    post(|| async { block(64269122) }),
+                ),
+        )
+        .await;
+        let adapter = TronAdapter::new(HttpClient::default());
+        assert_eq!(adapter.fetch_tip(&rpc).await.unwrap(), 64269141);
+        assert_eq!(
+            adapter
+                .fetch_by_tag(&rpc, BlockTag::FINALIZED)
+                .await
+                .unwrap(),
+            64269122
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_block_without_number() {
+        let rpc =
+            serve(Router::new().route("/wallet/getnowblock", post(|| async { Json(json!({})) })))
+                .await;
+        let adapter = TronAdapter::new(HttpClient::default());
+        assert!(adapter.fetch_tip(&rpc).await.is_err());
+    }
+}
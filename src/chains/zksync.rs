@@ -0,0 +1,79 @@
+use async_trait::async_trait;
+use serde_json::json;
+
+use super::{
+    evm::{fetch_evm_balances, fetch_evm_block_number, fetch_evm_header, new_heads_subscription},
+    http::HttpClient,
+    json_rpc_batch, parse_hex_u64, AdapterResult, BlockHeader, BlockTag, ChainAdapter,
+    LayerHeights, TipSubscription,
+};
+use crate::models::balances::BalanceQuery;
+
+/// zkSync Era publishes its own L2 height. Its L1 anchoring is the latest L1 batch
+/// from `zks_L1BatchNumber`, since batches rather than L1 blocks are what get
+/// committed to Ethereum.
+pub struct ZkSyncAdapter {
+    client: HttpClient,
+}
+
+impl ZkSyncAdapter {
+    pub fn new(client: HttpClient) -> Self {
+        ZkSyncAdapter { client }
+    }
+}
+
+#[async_trait]
+impl ChainAdapter for ZkSyncAdapter {
+    async fn fetch_tip(&self, rpc: &str) -> AdapterResult<u64> {
+        self.fetch_by_tag(rpc, BlockTag::LATEST).await
+    }
+
+    async fn fetch_by_tag(&self, rpc: &str, tag: BlockTag) -> AdapterResult<u64> {
+        fetch_evm_block_number(&self.client, rpc, tag).await
+    }
+
+    fn tip_subscription(&self) -> Option<TipSubscription> {
+        Some(new_heads_subscription())
+    }
+
+    fn is_layer2(&self) -> bool {
+        true
+    }
+
+    async fn fetch_layer_heights(&self, rpc: &str) -> AdapterResult<LayerHeights> {
+        let results = json_rpc_batch(
+            &self.client,
+            rpc,
+            &[
+                ("eth_blockNumber", json!([])),
+                ("zks_L1BatchNumber", json!([])),
+            ],
+        )
+        .await?;
+        Ok(LayerHeights {
+            l1: None,
+            l1_batch: Some(parse_hex_u64(&results[1])?),
+            l2: parse_hex_u64(&results[0])?,
+        })
+    }
+
+    fn supports_headers(&self) -> bool {
+        true
+    }
+
+    async fn fetch_header(&self, rpc: &str, height: u64) -> AdapterResult<BlockHeader> {
+        fetch_evm_header(&self.client, rpc, height).await
+    }
+
+    fn supports_balances(&self) -> bool {
+        true
+    }
+
+    async fn fetch_balances(
+        &self,
+        rpc: &str,
+        queries: &[BalanceQuery],
+    ) -> AdapterResult<Vec<Option<u128>>> {
+        fetch_evm_balances(&self.client, rpc, queries).await
+    }
+}
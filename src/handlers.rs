@@ -1,4 +1,9 @@
 pub mod assets;
+pub mod balances;
 pub mod block_numbers;
+pub mod commitments;
 pub mod health;
 pub mod notifications;
+pub mod reorgs;
+pub mod rpcs;
+pub mod ws;
@@ -7,6 +7,7 @@ use crate::{
     models::assets::{Asset, NetworkType},
 };
 
+#[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AssetData {
     pub networks: HashMap<String, NetworkResponse>,
@@ -32,7 +33,7 @@ pub async fn get_assets(
     State(appstate): State<Arc<AppState>>,
     network_type: Option<Path<NetworkType>>,
 ) -> Result<axum::Json<HashMap<String, NetworkResponse>>, axum::http::StatusCode> {
-    let mut response = HashMap::new();
+    let mut response;
     let cached_assets = appstate.cached_assets.clone();
     match network_type {
         Some(Path(network_type)) => match network_type {
@@ -0,0 +1,98 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+
+use crate::{
+    appstate::AppState,
+    handlers::commitments::active_reserved_amounts,
+    models::{
+        assets::NetworkType,
+        balances::{AssetBalance, UtxoAddressBalance},
+    },
+};
+
+/// Fills in `reserved` and `virtual` from the commitments active right now.
+pub async fn apply_commitments(
+    appstate: &AppState,
+    network_type: &NetworkType,
+    balances: &mut HashMap<String, Vec<AssetBalance>>,
+) -> Result<(), axum::http::StatusCode> {
+    let chains: Vec<String> = balances.keys().cloned().collect();
+    let reserved = active_reserved_amounts(appstate, network_type, &chains)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error: {}", e);
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    for (chain, assets) in balances.iter_mut() {
+        for asset in assets.iter_mut() {
+            let key = (chain.clone(), asset.token_address.to_lowercase());
+            asset.apply_reserved(reserved.get(&key).copied().unwrap_or(0));
+        }
+    }
+    Ok(())
+}
+
+pub async fn get_balances(
+    State(appstate): State<Arc<AppState>>,
+    Path(network_type): Path<NetworkType>,
+) -> Result<Json<HashMap<String, Vec<AssetBalance>>>, axum::http::StatusCode> {
+    let mut balances = appstate
+        .balances
+        .cache(&network_type)
+        .iter()
+        .map(|entry| ((*entry.0).clone(), entry.1))
+        .collect();
+    apply_commitments(&appstate, &network_type, &mut balances).await?;
+    Ok(Json(balances))
+}
+
+pub async fn get_asset_balance(
+    State(appstate): State<Arc<AppState>>,
+    Path((network_type, chain, asset)): Path<(NetworkType, String, String)>,
+) -> Result<Json<AssetBalance>, axum::http::StatusCode> {
+    let balances = appstate
+        .balances
+        .cache(&network_type)
+        .get(&chain)
+        .await
+        .ok_or(axum::http::StatusCode::NOT_FOUND)?;
+
+    let balance = balances
+        .into_iter()
+        .find(|balance| balance.matches(&asset))
+        .ok_or(axum::http::StatusCode::NOT_FOUND)?;
+
+    let mut balances = HashMap::from([(chain.clone(), vec![balance])]);
+    apply_commitments(&appstate, &network_type, &mut balances).await?;
+    balances
+        .remove(&chain)
+        .and_then(|mut assets| assets.pop())
+        .map(Json)
+        .ok_or(axum::http::StatusCode::NOT_FOUND)
+}
+
+pub async fn get_utxos(
+    State(appstate): State<Arc<AppState>>,
+    Path((network_type, chain)): Path<(NetworkType, String)>,
+) -> Result<Json<Vec<UtxoAddressBalance>>, axum::http::StatusCode> {
+    // utxos are cached by chain alone, so make sure the chain belongs to network_type
+    appstate
+        .balances
+        .networks
+        .get(&chain)
+        .filter(|network| network.network_type == network_type)
+        .ok_or(axum::http::StatusCode::NOT_FOUND)?;
+
+    appstate
+        .balances
+        .utxos
+        .get(&chain)
+        .await
+        .map(Json)
+        .ok_or(axum::http::StatusCode::NOT_FOUND)
+}
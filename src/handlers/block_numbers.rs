@@ -1,12 +1,30 @@
+#![allow(clippy::needless_return)]
+
 use std::{collections::HashMap, sync::Arc};
 
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
     Json,
 };
+use chrono::{DateTime, TimeDelta, Utc};
+use futures::{stream, Stream, StreamExt};
 use serde::{Deserialize, Serialize};
+use tokio_stream::wrappers::BroadcastStream;
 
-use crate::{appstate::AppState, models::assets::NetworkType};
+use crate::{
+    appstate::AppState,
+    cache::{
+        block_times::{estimate_height_at, estimate_time_of},
+        blocknumbers_cache::{BlockNumberDetails, BlockNumberUpdate},
+    },
+    chains::BlockTag,
+    models::{assets::NetworkType, block_history::BlockNumberRecord},
+};
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct BlockNumbersResponse {
@@ -18,12 +36,84 @@ pub struct BlockNumbersResponse {
     pub localnet: Option<HashMap<String, u64>>,
 }
 
+#[derive(Debug, Default, Deserialize)]
+pub struct BlockNumbersQuery {
+    /// Return when and where each block number was fetched instead of the bare number.
+    #[serde(default)]
+    pub detailed: bool,
+    /// Return `safe` or `finalized` heights instead of the latest ones.
+    pub finality: Option<BlockTag>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DetailedBlockNumbersResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mainnet: Option<HashMap<String, BlockNumberDetails>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub testnet: Option<HashMap<String, BlockNumberDetails>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub localnet: Option<HashMap<String, BlockNumberDetails>>,
+}
+
 pub async fn get_block_numbers(
     State(appstate): State<Arc<AppState>>,
     network_type: Option<Path<NetworkType>>,
-) -> Result<Json<BlockNumbersResponse>, axum::http::StatusCode> {
+    Query(query): Query<BlockNumbersQuery>,
+) -> Result<Response, axum::http::StatusCode> {
     let cached_block_numbers = appstate.block_numbers.clone();
 
+    if query.detailed {
+        let block_numbers = &cached_block_numbers;
+        let details = |network_type: NetworkType| async move {
+            Some(block_numbers.details(&network_type).await)
+        };
+        let response = match network_type {
+            Some(Path(NetworkType::MAINNET)) => DetailedBlockNumbersResponse {
+                mainnet: details(NetworkType::MAINNET).await,
+                testnet: None,
+                localnet: None,
+            },
+            Some(Path(NetworkType::TESTNET)) => DetailedBlockNumbersResponse {
+                mainnet: None,
+                testnet: details(NetworkType::TESTNET).await,
+                localnet: None,
+            },
+            Some(Path(NetworkType::LOCALNET)) => DetailedBlockNumbersResponse {
+                mainnet: None,
+                testnet: None,
+                localnet: details(NetworkType::LOCALNET).await,
+            },
+            None => DetailedBlockNumbersResponse {
+                mainnet: details(NetworkType::MAINNET).await,
+                testnet: details(NetworkType::TESTNET).await,
+                localnet: details(NetworkType::LOCALNET).await,
+            },
+        };
+        return Ok(Json(response).into_response());
+    }
+
+    if let Some(tag) = query.finality {
+        let block_numbers = &cached_block_numbers;
+        let requested = network_type.map(|Path(network_type)| network_type);
+        let heights = |network_type: NetworkType| {
+            let included = requested
+                .as_ref()
+                .is_none_or(|requested| *requested == network_type);
+            async move {
+                match included {
+                    true => Some(block_numbers.heights(&network_type, tag).await),
+                    false => None,
+                }
+            }
+        };
+        return Ok(Json(BlockNumbersResponse {
+            mainnet: heights(NetworkType::MAINNET).await,
+            testnet: heights(NetworkType::TESTNET).await,
+            localnet: heights(NetworkType::LOCALNET).await,
+        })
+        .into_response());
+    }
+
     match network_type {
         Some(Path(network_type)) => match network_type {
             NetworkType::TESTNET => {
@@ -37,7 +127,8 @@ pub async fn get_block_numbers(
                             .collect(),
                     ),
                     localnet: None,
-                }));
+                })
+                .into_response());
             }
             NetworkType::MAINNET => {
                 return Ok(Json(BlockNumbersResponse {
@@ -50,7 +141,8 @@ pub async fn get_block_numbers(
                     ),
                     testnet: None,
                     localnet: None,
-                }));
+                })
+                .into_response());
             }
             NetworkType::LOCALNET => {
                 return Ok(Json(BlockNumbersResponse {
@@ -63,7 +155,8 @@ pub async fn get_block_numbers(
                             .map(|entry| ((*entry.0).clone(), entry.1))
                             .collect(),
                     ),
-                }));
+                })
+                .into_response());
             }
         },
         None => {
@@ -90,7 +183,8 @@ pub async fn get_block_numbers(
                         .map(|entry| ((*entry.0).clone(), entry.1))
                         .collect(),
                 ),
-            }));
+            })
+            .into_response());
         }
     }
 }
@@ -98,9 +192,16 @@ pub async fn get_block_numbers(
 pub async fn get_block_numbers_by_chain(
     State(appstate): State<Arc<AppState>>,
     network_type: Path<NetworkType>,
-) -> Result<Json<HashMap<String, u64>>, axum::http::StatusCode> {
+    Query(query): Query<BlockNumbersQuery>,
+) -> Result<Response, axum::http::StatusCode> {
     let cached_block_numbers = appstate.block_numbers.clone();
     let network_type = network_type.0;
+    if query.detailed {
+        return Ok(Json(cached_block_numbers.details(&network_type).await).into_response());
+    }
+    if let Some(tag) = query.finality {
+        return Ok(Json(cached_block_numbers.heights(&network_type, tag).await).into_response());
+    }
     match network_type {
         NetworkType::TESTNET => {
             return Ok(Json(
@@ -108,8 +209,9 @@ pub async fn get_block_numbers_by_chain(
                     .testnet
                     .iter()
                     .map(|entry| ((*entry.0).clone(), entry.1))
-                    .collect(),
-            ));
+                    .collect::<HashMap<_, _>>(),
+            )
+            .into_response());
         }
         NetworkType::MAINNET => {
             return Ok(Json(
@@ -117,8 +219,9 @@ pub async fn get_block_numbers_by_chain(
                     .mainnet
                     .iter()
                     .map(|entry| ((*entry.0).clone(), entry.1))
-                    .collect(),
-            ));
+                    .collect::<HashMap<_, _>>(),
+            )
+            .into_response());
         }
         NetworkType::LOCALNET => {
             return Ok(Json(
@@ -126,8 +229,202 @@ pub async fn get_block_numbers_by_chain(
                     .localnet
                     .iter()
                     .map(|entry| ((*entry.0).clone(), entry.1))
-                    .collect(),
-            ));
+                    .collect::<HashMap<_, _>>(),
+            )
+            .into_response());
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EtaQuery {
+    pub target: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EtaResponse {
+    pub chain: String,
+    #[serde(rename = "currentBlockNumber")]
+    pub current_block_number: u64,
+    #[serde(rename = "targetBlockNumber")]
+    pub target_block_number: u64,
+    #[serde(rename = "averageBlockTimeSecs")]
+    pub average_block_time_secs: f64,
+    /// Seconds from now until the target height is expected, 0 once reached.
+    #[serde(rename = "etaSecs")]
+    pub eta_secs: u64,
+    /// Unix timestamp at which the target height is expected.
+    #[serde(rename = "estimatedTimestamp")]
+    pub estimated_timestamp: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AtQuery {
+    pub timestamp: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AtResponse {
+    pub chain: String,
+    pub timestamp: i64,
+    /// Estimated height of the chain at `timestamp`.
+    #[serde(rename = "blockNumber")]
+    pub block_number: u64,
+    #[serde(rename = "averageBlockTimeSecs")]
+    pub average_block_time_secs: f64,
+}
+
+/// The latest height of `chain` with the time it was produced and the average block
+/// time, or `SERVICE_UNAVAILABLE` until enough heights were seen to estimate it.
+async fn block_time_estimate(
+    appstate: &AppState,
+    network_type: &NetworkType,
+    chain: &str,
+) -> Result<(DateTime<Utc>, u64, f64), StatusCode> {
+    appstate
+        .block_numbers
+        .cache(network_type)
+        .get(chain)
+        .await
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let block_times = &appstate.block_numbers.block_times;
+    let (seen_at, height) = block_times
+        .latest(chain)
+        .ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    let average_block_time = block_times
+        .average_block_time(chain)
+        .ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    Ok((seen_at, height, average_block_time))
+}
+
+/// Estimates when `chain` will reach the `target` height.
+pub async fn get_block_eta(
+    State(appstate): State<Arc<AppState>>,
+    Path((network_type, chain)): Path<(NetworkType, String)>,
+    Query(query): Query<EtaQuery>,
+) -> Result<Json<EtaResponse>, StatusCode> {
+    let (seen_at, height, average_block_time) =
+        block_time_estimate(&appstate, &network_type, &chain).await?;
+
+    // targets so far ahead that the estimate doesn't fit in a timestamp are rejected
+    let estimated_at = estimate_time_of((seen_at, height), average_block_time, query.target)
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    let eta = (estimated_at - Utc::now()).max(TimeDelta::zero());
+
+    Ok(Json(EtaResponse {
+        chain,
+        current_block_number: height,
+        target_block_number: query.target,
+        average_block_time_secs: average_block_time,
+        eta_secs: eta.num_seconds() as u64,
+        estimated_timestamp: estimated_at.timestamp(),
+    }))
+}
+
+/// Estimates the height of `chain` at a unix `timestamp`, in the past or future.
+pub async fn get_block_at(
+    State(appstate): State<Arc<AppState>>,
+    Path((network_type, chain)): Path<(NetworkType, String)>,
+    Query(query): Query<AtQuery>,
+) -> Result<Json<AtResponse>, StatusCode> {
+    let (seen_at, height, average_block_time) =
+        block_time_estimate(&appstate, &network_type, &chain).await?;
+
+    let at = DateTime::from_timestamp(query.timestamp, 0).ok_or(StatusCode::BAD_REQUEST)?;
+    let block_number = estimate_height_at((seen_at, height), average_block_time, at);
+
+    Ok(Json(AtResponse {
+        chain,
+        timestamp: query.timestamp,
+        block_number,
+        average_block_time_secs: average_block_time,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HistoryQuery {
+    /// Unix timestamp (seconds), defaults to 24 hours before `to`.
+    pub from: Option<i64>,
+    /// Unix timestamp (seconds), defaults to now.
+    pub to: Option<i64>,
+}
+
+/// Returns the stored block number changes of `chain` between `from` and `to`.
+pub async fn get_block_number_history(
+    State(appstate): State<Arc<AppState>>,
+    Path((network_type, chain)): Path<(NetworkType, String)>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<Vec<BlockNumberRecord>>, StatusCode> {
+    appstate
+        .block_numbers
+        .cache(&network_type)
+        .get(&chain)
+        .await
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let to = match query.to {
+        Some(to) => DateTime::from_timestamp(to, 0).ok_or(StatusCode::BAD_REQUEST)?,
+        None => Utc::now(),
+    };
+    let from = match query.from {
+        Some(from) => DateTime::from_timestamp(from, 0).ok_or(StatusCode::BAD_REQUEST)?,
+        None => to - TimeDelta::days(1),
+    };
+
+    match appstate
+        .block_numbers
+        .history
+        .get_block_number_history(&chain, &network_type, from, to)
+        .await
+    {
+        Ok(records) => Ok(Json(records)),
+        Err(e) => {
+            eprintln!("Database error: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Streams block number changes as server-sent `blocknumber` events, starting with
+/// the currently cached value of every chain.
+pub async fn stream_block_numbers(
+    State(appstate): State<Arc<AppState>>,
+    network_type: Option<Path<NetworkType>>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    let network_types = match network_type {
+        Some(Path(network_type)) => vec![network_type],
+        None => vec![
+            NetworkType::MAINNET,
+            NetworkType::TESTNET,
+            NetworkType::LOCALNET,
+        ],
+    };
+
+    // subscribe before taking the snapshot so no change falls in between
+    let updates = BroadcastStream::new(appstate.block_numbers.updates.subscribe());
+
+    let mut snapshot = Vec::new();
+    for network_type in &network_types {
+        for (chain, block_number) in appstate.block_numbers.cache(network_type).iter() {
+            snapshot.push(BlockNumberUpdate {
+                network_type: network_type.clone(),
+                chain: (*chain).clone(),
+                block_number,
+            });
         }
     }
+
+    let updates = updates.filter_map(move |update| {
+        // lagging receivers just skip the updates they missed
+        let update = update
+            .ok()
+            .filter(|update| network_types.contains(&update.network_type));
+        async move { update }
+    });
+
+    let events = stream::iter(snapshot)
+        .chain(updates)
+        .map(|update| Event::default().event("blocknumber").json_data(update));
+
+    Sse::new(events).keep_alive(KeepAlive::default())
 }
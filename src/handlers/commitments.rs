@@ -0,0 +1,175 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use uuid::Uuid;
+
+use crate::{
+    appstate::AppState,
+    models::{assets::NetworkType, commitment::NewCommitment},
+    utils::{is_authorized, ApiResponse},
+};
+
+pub async fn add_commitment(
+    headers: axum::http::HeaderMap,
+    State(appstate): State<Arc<AppState>>,
+    Json(mut commitment): Json<NewCommitment>,
+) -> impl IntoResponse {
+    if !is_authorized(&headers) {
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(ApiResponse::error("Unauthorized")),
+        )
+            .into_response();
+    }
+
+    let network = match appstate.balances.networks.get(&commitment.chain) {
+        Some(network) => network,
+        None => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponse::error("Unknown chain")),
+            )
+                .into_response()
+        }
+    };
+
+    match network.asset_config.iter().find(|asset| {
+        asset.token_address.eq_ignore_ascii_case(&commitment.asset)
+            || asset.symbol.eq_ignore_ascii_case(&commitment.asset)
+    }) {
+        Some(asset) => commitment.asset = asset.token_address.clone(),
+        None => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponse::error("Unknown asset")),
+            )
+                .into_response()
+        }
+    }
+
+    if !commitment
+        .amount
+        .parse::<u128>()
+        .is_ok_and(|amount| amount > 0)
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error("Invalid amount")),
+        )
+            .into_response();
+    }
+
+    let expiry_blocks = match network.commitment_expiry_blocks {
+        Some(expiry_blocks) => expiry_blocks,
+        None => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ApiResponse::error("Commitments are not enabled for chain")),
+            )
+                .into_response()
+        }
+    };
+
+    // expiry is counted in the chain's own blocks, even on L2s publishing their L1
+    // height
+    let current_block = match appstate
+        .block_numbers
+        .own_height(&commitment.chain, &network.network_type)
+        .await
+    {
+        Some(blocknumber) => blocknumber,
+        None => {
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(ApiResponse::error("Block number not available yet")),
+            )
+                .into_response()
+        }
+    };
+
+    match appstate
+        .commitments
+        .create_commitment(commitment, current_block + expiry_blocks)
+        .await
+    {
+        Ok(commitment) => (StatusCode::CREATED, Json(ApiResponse::ok(commitment))).into_response(),
+        Err(e) => {
+            eprintln!("Database error: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error("Failed to create commitment")),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub async fn release_commitment(
+    headers: axum::http::HeaderMap,
+    State(appstate): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    if !is_authorized(&headers) {
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(ApiResponse::error("Unauthorized")),
+        );
+    }
+
+    let Ok(id) = Uuid::parse_str(&id) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error("Invalid commitment id")),
+        );
+    };
+
+    match appstate.commitments.release_commitment(id).await {
+        Ok(true) => (StatusCode::OK, Json(ApiResponse::ok("Commitment released"))),
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Json(ApiResponse::error("No active commitment found")),
+        ),
+        Err(e) => {
+            eprintln!("Database error: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error("Failed to release commitment")),
+            )
+        }
+    }
+}
+
+/// Sums the unreleased, unexpired commitments of `chains`, keyed by chain and
+/// lowercased asset address.
+pub async fn active_reserved_amounts(
+    appstate: &AppState,
+    network_type: &NetworkType,
+    chains: &[String],
+) -> anyhow::Result<HashMap<(String, String), u128>> {
+    let mut block_numbers = Vec::with_capacity(chains.len());
+    for chain in chains {
+        let height = appstate
+            .block_numbers
+            .own_height(chain, network_type)
+            .await
+            .unwrap_or(0);
+        block_numbers.push((chain.clone(), height));
+    }
+    let commitments = appstate
+        .commitments
+        .get_active_commitments(&block_numbers)
+        .await?;
+
+    let mut reserved = HashMap::new();
+    for commitment in commitments {
+        *reserved
+            .entry((commitment.chain, commitment.asset.to_lowercase()))
+            .or_insert(0) += commitment.amount.parse::<u128>().unwrap_or(0);
+    }
+    Ok(reserved)
+}
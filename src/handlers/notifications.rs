@@ -1,3 +1,5 @@
+#![allow(clippy::needless_return)]
+
 use axum::{
     extract::{Path, State},
     http::StatusCode,
@@ -0,0 +1,41 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use serde::Deserialize;
+
+use crate::{appstate::AppState, cache::reorgs::ReorgEvent, models::assets::NetworkType};
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ReorgsQuery {
+    pub chain: Option<String>,
+}
+
+/// Returns the detected reorgs, newest first, optionally filtered by network type
+/// and chain.
+pub async fn get_reorgs(
+    State(appstate): State<Arc<AppState>>,
+    network_type: Option<Path<NetworkType>>,
+    Query(query): Query<ReorgsQuery>,
+) -> Result<Json<Vec<ReorgEvent>>, axum::http::StatusCode> {
+    let reorgs = appstate
+        .block_numbers
+        .reorgs
+        .reorgs()
+        .into_iter()
+        .filter(|reorg| {
+            network_type
+                .as_ref()
+                .is_none_or(|Path(network_type)| reorg.network_type == *network_type)
+        })
+        .filter(|reorg| {
+            query
+                .chain
+                .as_ref()
+                .is_none_or(|chain| reorg.chain == *chain)
+        })
+        .collect();
+    Ok(Json(reorgs))
+}
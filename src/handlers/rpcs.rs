@@ -0,0 +1,18 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{extract::State, Json};
+
+use crate::{appstate::AppState, cache::rpc_health::RpcHealthReport};
+
+pub async fn get_rpc_health(
+    State(appstate): State<Arc<AppState>>,
+) -> Result<Json<HashMap<String, Vec<RpcHealthReport>>>, axum::http::StatusCode> {
+    let block_numbers = appstate.block_numbers.clone();
+    Ok(Json(
+        block_numbers
+            .rpcs
+            .iter()
+            .map(|(chain, rpcs)| (chain.clone(), block_numbers.health.report(chain, rpcs)))
+            .collect(),
+    ))
+}
@@ -0,0 +1,264 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    response::IntoResponse,
+};
+use moka::future::{Cache, CacheBuilder};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::{
+    appstate::AppState,
+    cache::{balances_cache::BalanceUpdate, blocknumbers_cache::BlockNumberUpdate},
+    handlers::balances::apply_commitments,
+    models::assets::NetworkType,
+};
+
+const NETWORK_TYPES: [NetworkType; 3] = [
+    NetworkType::MAINNET,
+    NetworkType::TESTNET,
+    NetworkType::LOCALNET,
+];
+
+/// Balance updates with `reserved` and `virtual` filled in. They're adjusted once per
+/// update and shared by every socket, so commitment lookups don't grow with the number
+/// of connections.
+pub struct BalanceFeed {
+    pub updates: broadcast::Sender<BalanceUpdate>,
+    /// Last adjusted balances of each chain, keyed by network type and chain, sent to
+    /// new subscribers.
+    latest: Cache<(String, String), BalanceUpdate>,
+}
+
+impl Default for BalanceFeed {
+    fn default() -> Self {
+        BalanceFeed {
+            updates: broadcast::channel(64).0,
+            latest: CacheBuilder::new(100).build(),
+        }
+    }
+}
+
+impl BalanceFeed {
+    /// Adjusts every balance update for the commitments active at that moment and
+    /// publishes it to the sockets.
+    pub async fn forward(&self, appstate: &AppState) {
+        let mut updates = appstate.balances.updates.subscribe();
+        loop {
+            let mut update = match updates.recv().await {
+                Ok(update) => update,
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return,
+            };
+            let mut balances = HashMap::from([(update.chain.clone(), update.balances)]);
+            if apply_commitments(appstate, &update.network_type, &mut balances)
+                .await
+                .is_err()
+            {
+                continue;
+            }
+            update.balances = balances.remove(&update.chain).unwrap_or_default();
+            let key = (
+                update.network_type.as_str().to_string(),
+                update.chain.clone(),
+            );
+            self.latest.insert(key, update.clone()).await;
+            // sending only fails when no socket is connected
+            let _ = self.updates.send(update);
+        }
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+enum ClientMessage {
+    SUBSCRIBE { topics: Vec<String> },
+    UNSUBSCRIBE { topics: Vec<String> },
+}
+
+/// Topics look like `blocknumbers`, `blocknumbers:testnet` or
+/// `blocknumbers:testnet:bitcoin_testnet` (same for `balances`), plus `notifications`.
+fn is_valid_topic(topic: &str) -> bool {
+    let mut parts = topic.split(':');
+    match parts.next() {
+        Some("notifications") => parts.next().is_none(),
+        Some("blocknumbers") | Some("balances") => match parts.next() {
+            None => true,
+            Some(network) => {
+                NETWORK_TYPES
+                    .iter()
+                    .any(|network_type| network_type.as_str() == network)
+                    && parts.next().is_none_or(|chain| !chain.is_empty())
+                    && parts.next().is_none()
+            }
+        },
+        _ => false,
+    }
+}
+
+/// Returns the most specific topic of an event if the client subscribed to it or to
+/// any of its parents.
+fn matching_topic(
+    topics: &HashSet<String>,
+    kind: &str,
+    network_type: &NetworkType,
+    chain: &str,
+) -> Option<String> {
+    let network_topic = format!("{}:{}", kind, network_type.as_str());
+    let chain_topic = format!("{}:{}", network_topic, chain);
+    (topics.contains(kind) || topics.contains(&network_topic) || topics.contains(&chain_topic))
+        .then_some(chain_topic)
+}
+
+async fn send_json(socket: &mut WebSocket, value: &impl Serialize) -> bool {
+    match serde_json::to_string(value) {
+        Ok(text) => socket.send(Message::Text(text.into())).await.is_ok(),
+        Err(_) => true,
+    }
+}
+
+/// Sends the currently cached block numbers and balances covered by `topics`.
+async fn send_snapshot(
+    socket: &mut WebSocket,
+    appstate: &AppState,
+    topics: &HashSet<String>,
+) -> bool {
+    for network_type in &NETWORK_TYPES {
+        for (chain, block_number) in appstate.block_numbers.cache(network_type).iter() {
+            if let Some(topic) = matching_topic(topics, "blocknumbers", network_type, &chain) {
+                let data = json!({
+                    "networkType": network_type,
+                    "chain": *chain,
+                    "blockNumber": block_number,
+                });
+                if !send_json(socket, &json!({ "topic": topic, "data": data })).await {
+                    return false;
+                }
+            }
+        }
+
+        for (key, update) in appstate.balance_feed.latest.iter() {
+            if key.0 != network_type.as_str() {
+                continue;
+            }
+            if let Some(topic) = matching_topic(topics, "balances", network_type, &key.1) {
+                if !send_json(socket, &json!({ "topic": topic, "data": update })).await {
+                    return false;
+                }
+            }
+        }
+    }
+    true
+}
+
+/// Applies a subscribe/unsubscribe request and returns the reply for the client.
+fn handle_client_message(text: &str, topics: &mut HashSet<String>) -> (Value, HashSet<String>) {
+    match serde_json::from_str::<ClientMessage>(text) {
+        Ok(ClientMessage::SUBSCRIBE { topics: requested }) => {
+            if let Some(invalid) = requested.iter().find(|topic| !is_valid_topic(topic)) {
+                let reply =
+                    json!({ "event": "error", "message": format!("Invalid topic {}", invalid) });
+                return (reply, HashSet::new());
+            }
+            let added: HashSet<String> = requested
+                .into_iter()
+                .filter(|topic| topics.insert(topic.clone()))
+                .collect();
+            (json!({ "event": "subscribed", "topics": added }), added)
+        }
+        Ok(ClientMessage::UNSUBSCRIBE { topics: requested }) => {
+            for topic in &requested {
+                topics.remove(topic);
+            }
+            (
+                json!({ "event": "unsubscribed", "topics": requested }),
+                HashSet::new(),
+            )
+        }
+        Err(e) => (
+            json!({ "event": "error", "message": format!("Invalid message: {}", e) }),
+            HashSet::new(),
+        ),
+    }
+}
+
+pub async fn ws_handler(
+    ws: WebSocketUpgrade,
+    State(appstate): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    ws.on_upgrade(move |socket| handle_socket(socket, appstate))
+}
+
+async fn forward_block_number(
+    socket: &mut WebSocket,
+    topics: &HashSet<String>,
+    update: BlockNumberUpdate,
+) -> bool {
+    match matching_topic(topics, "blocknumbers", &update.network_type, &update.chain) {
+        Some(topic) => send_json(socket, &json!({ "topic": topic, "data": update })).await,
+        None => true,
+    }
+}
+
+async fn forward_balances(
+    socket: &mut WebSocket,
+    topics: &HashSet<String>,
+    update: BalanceUpdate,
+) -> bool {
+    match matching_topic(topics, "balances", &update.network_type, &update.chain) {
+        Some(topic) => send_json(socket, &json!({ "topic": topic, "data": update })).await,
+        None => true,
+    }
+}
+
+async fn handle_socket(mut socket: WebSocket, appstate: Arc<AppState>) {
+    let mut topics = HashSet::new();
+    let mut block_numbers = appstate.block_numbers.updates.subscribe();
+    let mut balances = appstate.balance_feed.updates.subscribe();
+    let mut notifications = appstate.notifications.updates.subscribe();
+
+    // every branch evaluates to whether the socket is still usable
+    loop {
+        let open = tokio::select! {
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    let (reply, added) = handle_client_message(&text, &mut topics);
+                    send_json(&mut socket, &reply).await
+                        && send_snapshot(&mut socket, &appstate, &added).await
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => false,
+                Some(Ok(_)) => true,
+            },
+            update = block_numbers.recv() => match update {
+                Ok(update) => forward_block_number(&mut socket, &topics, update).await,
+                Err(RecvError::Lagged(_)) => true,
+                Err(RecvError::Closed) => false,
+            },
+            update = balances.recv() => match update {
+                Ok(update) => forward_balances(&mut socket, &topics, update).await,
+                Err(RecvError::Lagged(_)) => true,
+                Err(RecvError::Closed) => false,
+            },
+            update = notifications.recv() => match update {
+                Ok(update) if topics.contains("notifications") => {
+                    send_json(&mut socket, &json!({ "topic": "notifications", "data": update })).await
+                }
+                Ok(_) | Err(RecvError::Lagged(_)) => true,
+                Err(RecvError::Closed) => false,
+            },
+        };
+
+        if !open {
+            break;
+        }
+    }
+}
@@ -3,21 +3,35 @@ use std::sync::Arc;
 use appstate::AppState;
 use axum::routing::post;
 use axum::{routing::get, Router};
-use cache::{assets_cache::AssetsCache, blocknumbers_cache::BlockNumbers};
+use cache::{
+    assets_cache::AssetsCache, balances_cache::Balances, blocknumbers_cache::BlockNumbers,
+};
 use dotenv::dotenv;
 use handlers::assets::get_assets;
-use handlers::block_numbers::{get_block_numbers, get_block_numbers_by_chain};
+use handlers::balances::{get_asset_balance, get_balances, get_utxos};
+use handlers::block_numbers::{
+    get_block_at, get_block_eta, get_block_number_history, get_block_numbers,
+    get_block_numbers_by_chain, stream_block_numbers,
+};
+use handlers::commitments::{add_commitment, release_commitment};
 use handlers::health::health_check;
 use handlers::notifications::{
     add_notification, get_all_notifications, get_latest_notification, get_notification_by_id,
     set_latest_notification, update_notifications,
 };
+use handlers::reorgs::get_reorgs;
+use handlers::rpcs::get_rpc_health;
+use handlers::ws::{ws_handler, BalanceFeed};
+use models::block_history::BlockHistoryRepo;
+use models::commitment::CommitmentRepo;
 use models::notification::NotificationRepo;
 use reqwest::Method;
+use sqlx::PgPool;
 use tokio::net::TcpListener;
 use tower_http::cors::{AllowHeaders, Any, CorsLayer};
 mod appstate;
 mod cache;
+mod chains;
 mod handlers;
 mod models;
 mod utils;
@@ -27,26 +41,63 @@ async fn main() {
     dotenv().ok();
     let host = "0.0.0.0";
     let port = "3001";
-    let cached_assets = Arc::new(AssetsCache::new());
-    let block_numbers = Arc::new(BlockNumbers::new().await);
+    let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    let pool = PgPool::connect(&database_url)
+        .await
+        .expect("Failed to connect to database");
+
+    let block_history = Arc::new(
+        BlockHistoryRepo::new(pool.clone())
+            .await
+            .expect("Failed to create block history repo"),
+    );
+
+    // read once, so every cache serves the same networks
+    let config = utils::load_config();
+    let cached_assets = Arc::new(AssetsCache::new(&config));
+    let block_numbers = Arc::new(
+        BlockNumbers::new(block_history, &config)
+            .await
+            .expect("Failed to load block number config"),
+    );
+    let balances = Arc::new(Balances::new(&block_numbers, &config));
 
     let notifications = Arc::new(
-        NotificationRepo::new()
+        NotificationRepo::new(pool.clone())
             .await
             .expect("Failed to create notification repo"),
     );
+    let commitments = Arc::new(
+        CommitmentRepo::new(pool.clone())
+            .await
+            .expect("Failed to create commitment repo"),
+    );
 
     let appstate = Arc::new(AppState {
         cached_assets,
         block_numbers: block_numbers.clone(),
+        balances: balances.clone(),
+        balance_feed: Arc::new(BalanceFeed::default()),
         notifications,
+        commitments,
     });
 
-    // spawn a new thread to update the block numbers every 5 seconds
+    // poll every chain for its block number on its own interval
     tokio::spawn(async move {
         block_numbers.start_cron().await;
     });
 
+    // refresh filler balances in the background
+    tokio::spawn(async move {
+        balances.start_cron().await;
+    });
+
+    // adjust balance updates for commitments once for every websocket
+    let feed_state = appstate.clone();
+    tokio::spawn(async move {
+        feed_state.balance_feed.forward(&feed_state).await;
+    });
+
     let cors = CorsLayer::new()
         .allow_methods(vec![Method::GET, Method::POST])
         .allow_origin(Any)
@@ -59,14 +110,40 @@ async fn main() {
             "/blocknumbers/{network_type}",
             get(get_block_numbers_by_chain),
         )
+        .route(
+            "/blocknumbers/{network_type}/{chain}/eta",
+            get(get_block_eta),
+        )
+        .route("/blocknumbers/{network_type}/{chain}/at", get(get_block_at))
+        .route(
+            "/blocknumbers/{network_type}/{chain}/history",
+            get(get_block_number_history),
+        )
         .route("/blocknumbers", get(get_block_numbers))
+        .route("/blocknumbers/stream", get(stream_block_numbers))
+        .route(
+            "/blocknumbers/stream/{network_type}",
+            get(stream_block_numbers),
+        )
+        .route("/reorgs", get(get_reorgs))
+        .route("/reorgs/{network_type}", get(get_reorgs))
+        .route("/balances/{network_type}", get(get_balances))
+        .route(
+            "/balances/{network_type}/{chain}/{asset}",
+            get(get_asset_balance),
+        )
+        .route("/utxos/{network_type}/{chain}", get(get_utxos))
         .route("/health", get(health_check))
+        .route("/rpcs/health", get(get_rpc_health))
+        .route("/ws", get(ws_handler))
         .route("/notification", post(add_notification))
         .route("/notification/{id}", get(get_notification_by_id))
         .route("/notification", get(get_latest_notification))
         .route("/notifications", get(get_all_notifications))
         .route("/update/notification", post(update_notifications))
         .route("/latest/{id}", get(set_latest_notification))
+        .route("/commitment", post(add_commitment))
+        .route("/commitment/{id}/release", post(release_commitment))
         .layer(cors)
         .with_state(appstate);
 
@@ -1,3 +1,6 @@
 pub mod assets;
+pub mod balances;
+pub mod block_history;
+pub mod commitment;
 pub mod notification;
 pub mod response;
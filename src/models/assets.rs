@@ -1,6 +1,13 @@
+use std::{collections::HashMap, time::Duration};
+
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 
+/// Lower bound on the default `stale_after`.
+const MIN_STALE_AFTER: Duration = Duration::from_secs(60);
+/// Lower bound on `pollIntervalMs`, so a misconfigured chain can't flood its rpcs.
+pub const MIN_POLL_INTERVAL_MS: u64 = 100;
+
+#[allow(clippy::upper_case_acronyms)]
 #[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "lowercase")]
 pub enum NetworkType {
@@ -9,6 +16,54 @@ pub enum NetworkType {
     LOCALNET,
 }
 
+impl NetworkType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NetworkType::MAINNET => "mainnet",
+            NetworkType::TESTNET => "testnet",
+            NetworkType::LOCALNET => "localnet",
+        }
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ChainFamily {
+    #[default]
+    EVM,
+    ARBITRUM,
+    OPSTACK,
+    ZKSYNC,
+    SCROLL,
+    BITCOIN,
+    LITECOIN,
+    DOGECOIN,
+    BITCOINCASH,
+    SOLANA,
+    STARKNET,
+    SUI,
+    APTOS,
+    TRON,
+    TON,
+    COSMOS,
+}
+
+impl ChainFamily {
+    /// The heights that `primaryHeight` may pick from, empty for families that only
+    /// have one height to publish.
+    pub fn primary_heights(&self) -> &'static [HeightKind] {
+        match self {
+            ChainFamily::ARBITRUM | ChainFamily::OPSTACK | ChainFamily::SCROLL => {
+                &[HeightKind::L1, HeightKind::L2]
+            }
+            // the L1 anchoring of zkSync is a batch number, not a block height
+            ChainFamily::ZKSYNC => &[HeightKind::L2],
+            _ => &[],
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Network {
     #[serde(rename = "chainId")]
@@ -18,17 +73,312 @@ pub struct Network {
     pub explorer: String,
     #[serde(rename = "networkType")]
     pub network_type: NetworkType,
+    /// Defaults to `evm`, the family of every network configured before it existed.
+    #[serde(rename = "chainFamily", default)]
+    pub chain_family: ChainFamily,
     pub name: String,
     #[serde(rename = "assetConfig")]
     pub asset_config: Vec<Asset>,
     #[serde(skip_serializing)]
-    pub rpcs: Vec<String>,
+    pub rpcs: Vec<RpcEndpoint>,
+    #[serde(rename = "fillerAddresses", default)]
+    pub filler_addresses: Vec<String>,
+    /// Number of blocks after which a liquidity commitment on this chain expires,
+    /// counted in the chain's own blocks (L2 blocks on L2s, whatever `primaryHeight`
+    /// publishes). Commitments are rejected for chains that leave it unset.
+    #[serde(rename = "commitmentExpiryBlocks", default)]
+    pub commitment_expiry_blocks: Option<u64>,
+    /// When set, every rpc is queried on each tick and the published block number is
+    /// agreed on by all of them instead of taken from the first one that answers.
+    #[serde(rename = "rpcQuorum", default)]
+    pub rpc_quorum: Option<QuorumConfig>,
+    /// For L2 families, whether the L1 or the L2 height is published as the chain's
+    /// block number. Each family picks its own default when unset, and heights a
+    /// family can't publish are rejected when the config is loaded.
+    #[serde(rename = "primaryHeight", default)]
+    pub primary_height: Option<HeightKind>,
+    /// For solana, whether the slot or the block height is published as the chain's
+    /// block number.
+    #[serde(rename = "solanaHeight", default)]
+    pub solana_height: SolanaHeight,
+    /// For solana, the commitment at which the latest slot and block height are read.
+    #[serde(rename = "solanaCommitment", default)]
+    pub solana_commitment: SolanaCommitment,
+    /// Depths below the tip at which bitcoin-family blocks count as safe and finalized.
+    #[serde(rename = "finalityConfirmations", default)]
+    pub finality_confirmations: FinalityConfirmations,
+    /// Seconds without a successful block number fetch after which the cached block
+    /// number is reported as stale. See `stale_after` for the default.
+    #[serde(
+        rename = "staleAfterSecs",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub stale_after_secs: Option<u64>,
+    /// Milliseconds between two block number fetches of this chain, raised to
+    /// `MIN_POLL_INTERVAL_MS` when the config is loaded.
+    #[serde(rename = "pollIntervalMs", default = "default_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+    /// Timeouts and proxy of the http client used for this chain's rpcs.
+    #[serde(skip_serializing, default)]
+    pub http: HttpConfig,
     #[serde(default = "default_disabled")]
     pub disabled: Option<bool>,
 }
 fn default_disabled() -> Option<bool> {
     Some(false)
 }
+fn default_poll_interval_ms() -> u64 {
+    5000
+}
+
+impl Network {
+    /// How long the block number may go without a successful fetch before it is
+    /// stale: `staleAfterSecs`, or three poll intervals and at least a minute.
+    pub fn stale_after(&self) -> Duration {
+        match self.stale_after_secs {
+            Some(secs) => Duration::from_secs(secs),
+            None => (Duration::from_millis(self.poll_interval_ms) * 3).max(MIN_STALE_AFTER),
+        }
+    }
+}
+
+/// Protocol spoken by a bitcoin-family rpc.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(PartialEq, Debug, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RpcBackend {
+    /// Esplora REST api, e.g. `https://mempool.space/api`.
+    #[default]
+    ESPLORA,
+    /// Bitcoin Core JSON-RPC.
+    BITCOIND,
+    /// Electrum protocol over `tcp://` or `ssl://`.
+    ELECTRUM,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct HttpConfig {
+    #[serde(rename = "connectTimeoutMs", default = "default_connect_timeout_ms")]
+    pub connect_timeout_ms: u64,
+    /// Limit on a whole request, from connecting until the response body is read.
+    #[serde(rename = "requestTimeoutMs", default = "default_request_timeout_ms")]
+    pub request_timeout_ms: u64,
+    /// Proxy every request is sent through, e.g. `http://proxy.internal:3128`.
+    pub proxy: Option<String>,
+    /// Most calls sent in one JSON-RPC batch request; longer batches are split, as
+    /// public rpcs commonly reject large ones.
+    #[serde(rename = "maxBatchSize", default = "default_max_batch_size")]
+    pub max_batch_size: usize,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        HttpConfig {
+            connect_timeout_ms: default_connect_timeout_ms(),
+            request_timeout_ms: default_request_timeout_ms(),
+            proxy: None,
+            max_batch_size: default_max_batch_size(),
+        }
+    }
+}
+fn default_connect_timeout_ms() -> u64 {
+    5000
+}
+fn default_request_timeout_ms() -> u64 {
+    10000
+}
+fn default_max_batch_size() -> usize {
+    100
+}
+
+/// How often a request to an rpc is retried when it times out, fails to connect or
+/// gets a 429, 502, 503 or 504 response.
+#[derive(Debug, Deserialize, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Total number of tries, including the first one.
+    #[serde(default = "default_retry_attempts")]
+    pub attempts: u32,
+    /// Wait before the first retry, doubled before each later one.
+    #[serde(rename = "backoffMs", default = "default_retry_backoff_ms")]
+    pub backoff_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            attempts: default_retry_attempts(),
+            backoff_ms: default_retry_backoff_ms(),
+        }
+    }
+}
+fn default_retry_attempts() -> u32 {
+    2
+}
+fn default_retry_backoff_ms() -> u64 {
+    250
+}
+
+/// An rpc as configured in `rpcs`: either a plain url or an object that also picks
+/// its backend, credentials, headers, retry policy and websocket url.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(from = "RpcEntry")]
+pub struct RpcEndpoint {
+    pub url: String,
+    pub backend: RpcBackend,
+    /// Sent as basic auth on every request to the rpc.
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Extra headers sent on every request to the rpc, e.g. an api key.
+    pub headers: HashMap<String, String>,
+    pub retry: RetryPolicy,
+    /// Websocket url of the same rpc, used to subscribe to new tips.
+    pub ws: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RpcEntry {
+    Url(String),
+    Endpoint {
+        url: String,
+        #[serde(default)]
+        backend: RpcBackend,
+        username: Option<String>,
+        password: Option<String>,
+        #[serde(default)]
+        headers: HashMap<String, String>,
+        #[serde(default)]
+        retry: RetryPolicy,
+        ws: Option<String>,
+    },
+}
+
+impl From<RpcEntry> for RpcEndpoint {
+    fn from(entry: RpcEntry) -> Self {
+        match entry {
+            RpcEntry::Url(url) => RpcEndpoint {
+                url,
+                backend: RpcBackend::default(),
+                username: None,
+                password: None,
+                headers: HashMap::new(),
+                retry: RetryPolicy::default(),
+                ws: None,
+            },
+            RpcEntry::Endpoint {
+                url,
+                backend,
+                username,
+                password,
+                headers,
+                retry,
+                ws,
+            } => RpcEndpoint {
+                url,
+                backend,
+                username,
+                password,
+                headers,
+                retry,
+                ws,
+            },
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum HeightKind {
+    /// The height of the L1 the chain settles to, as seen by the L2.
+    L1,
+    /// The L2's own block number.
+    L2,
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SolanaHeight {
+    /// Slot number, which advances even when a leader skips its slot.
+    #[default]
+    SLOT,
+    /// Number of blocks actually produced, as used by `lastValidBlockHeight`.
+    BLOCKHEIGHT,
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SolanaCommitment {
+    PROCESSED,
+    #[default]
+    CONFIRMED,
+    FINALIZED,
+}
+
+impl SolanaCommitment {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SolanaCommitment::PROCESSED => "processed",
+            SolanaCommitment::CONFIRMED => "confirmed",
+            SolanaCommitment::FINALIZED => "finalized",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct FinalityConfirmations {
+    #[serde(default = "default_safe_confirmations")]
+    pub safe: u64,
+    #[serde(default = "default_finalized_confirmations")]
+    pub finalized: u64,
+}
+fn default_safe_confirmations() -> u64 {
+    1
+}
+fn default_finalized_confirmations() -> u64 {
+    6
+}
+
+impl Default for FinalityConfirmations {
+    fn default() -> Self {
+        FinalityConfirmations {
+            safe: default_safe_confirmations(),
+            finalized: default_finalized_confirmations(),
+        }
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum QuorumStrategy {
+    /// Publish the median of all reported heights.
+    #[default]
+    MEDIAN,
+    /// Publish the highest height that is within `tolerance` of the median.
+    MAX,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QuorumConfig {
+    #[serde(default)]
+    pub strategy: QuorumStrategy,
+    /// Rpcs further than this many blocks from the median are flagged and ignored.
+    #[serde(default = "default_tolerance")]
+    pub tolerance: u64,
+    /// Minimum number of agreeing rpcs needed to publish a new height.
+    #[serde(rename = "minResponses", default = "default_min_responses")]
+    pub min_responses: usize,
+}
+fn default_tolerance() -> u64 {
+    2
+}
+
+fn default_min_responses() -> usize {
+    1
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Asset {
@@ -48,6 +398,7 @@ pub struct Asset {
     pub disabled: Option<bool>,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BlockchainConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -58,6 +409,7 @@ pub struct BlockchainConfig {
     pub localnet: Option<HashMap<String, NetworkRpc>>,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct NetworkRpc {
     pub rpc: Vec<String>,
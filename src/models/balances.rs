@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A single balance lookup: `address`'s holdings of `token_address` on one chain.
+#[derive(Debug, Clone)]
+pub struct BalanceQuery {
+    pub token_address: String,
+    pub address: String,
+}
+
+/// Balances of every configured filler address for one asset. Amounts are in the
+/// asset's smallest unit and serialized as strings, since they can exceed `u64`.
+/// `virtual` is the on-chain `total` minus the amounts `reserved` by active commitments.
+#[derive(Debug, Clone, Serialize)]
+pub struct AssetBalance {
+    pub symbol: String,
+    pub decimals: u8,
+    #[serde(rename = "tokenAddress")]
+    pub token_address: String,
+    pub balances: HashMap<String, String>,
+    pub total: String,
+    pub reserved: String,
+    #[serde(rename = "virtual")]
+    pub virtual_balance: String,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: DateTime<Utc>,
+}
+
+impl AssetBalance {
+    pub fn apply_reserved(&mut self, reserved: u128) {
+        let total: u128 = self.total.parse().unwrap_or(0);
+        self.reserved = reserved.to_string();
+        self.virtual_balance = total.saturating_sub(reserved).to_string();
+    }
+
+    pub fn matches(&self, asset: &str) -> bool {
+        self.token_address.eq_ignore_ascii_case(asset) || self.symbol.eq_ignore_ascii_case(asset)
+    }
+}
+
+/// Read from Esplora in snake_case and served in camelCase.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct UtxoStatus {
+    pub confirmed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_height: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_time: Option<u64>,
+}
+
+/// An unspent output as returned by Esplora's `/address/{addr}/utxo`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Utxo {
+    pub txid: String,
+    pub vout: u32,
+    pub value: u64,
+    pub status: UtxoStatus,
+}
+
+/// Spendable (`confirmed`) and pending (`unconfirmed`, may be negative while spends
+/// sit in the mempool) satoshis of a UTXO chain address.
+#[derive(Debug, Clone, Serialize)]
+pub struct UtxoAddressBalance {
+    pub address: String,
+    pub confirmed: u64,
+    pub unconfirmed: i64,
+    pub utxos: Vec<Utxo>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: DateTime<Utc>,
+}
@@ -0,0 +1,199 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use anyhow::Result;
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+
+use super::assets::NetworkType;
+
+/// Upper bound on the rows returned by a single history query.
+const MAX_HISTORY_ROWS: i64 = 10_000;
+/// Minimum time between two stored block numbers of a chain.
+const SAMPLE_INTERVAL: TimeDelta = TimeDelta::seconds(30);
+/// How long stored block numbers are kept.
+pub const RETENTION: TimeDelta = TimeDelta::days(30);
+
+/// A block number fetched for a chain. `source_rpc` is the rpc's host only, since
+/// full rpc urls may contain api keys.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct BlockNumberRecord {
+    #[serde(skip_serializing)]
+    pub chain: String,
+    #[serde(skip_serializing)]
+    pub network_type: String,
+    pub height: i64,
+    #[serde(rename = "sourceRpc")]
+    pub source_rpc: String,
+    #[serde(rename = "fetchedAt")]
+    pub fetched_at: DateTime<Utc>,
+}
+
+/// Stores sampled block numbers. Samples are buffered and written in batches by
+/// `flush`, off the polling path.
+pub struct BlockHistoryRepo {
+    pool: PgPool,
+    pending: Mutex<Vec<BlockNumberRecord>>,
+    /// When each chain was last sampled.
+    sampled_at: Mutex<HashMap<String, DateTime<Utc>>>,
+}
+
+impl BlockHistoryRepo {
+    pub async fn new(pool: PgPool) -> Result<Self> {
+        sqlx::query(
+            "
+            CREATE TABLE IF NOT EXISTS block_number_history (
+                id BIGSERIAL PRIMARY KEY,
+                chain TEXT NOT NULL,
+                network_type TEXT NOT NULL,
+                height BIGINT NOT NULL,
+                source_rpc TEXT NOT NULL,
+                fetched_at TIMESTAMPTZ NOT NULL
+            )
+        ",
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query(
+            "
+            CREATE INDEX IF NOT EXISTS block_number_history_chain_idx
+            ON block_number_history (chain, fetched_at)
+        ",
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query(
+            "
+            CREATE INDEX IF NOT EXISTS block_number_history_fetched_at_idx
+            ON block_number_history (fetched_at)
+        ",
+        )
+        .execute(&pool)
+        .await?;
+
+        Ok(Self {
+            pool,
+            pending: Mutex::default(),
+            sampled_at: Mutex::default(),
+        })
+    }
+
+    /// Buffers `height` for the next `flush`, unless `chain` was already sampled
+    /// within the last `SAMPLE_INTERVAL`.
+    pub fn record_block_number(
+        &self,
+        chain: &str,
+        network_type: &NetworkType,
+        height: u64,
+        source_rpc: &str,
+        fetched_at: DateTime<Utc>,
+    ) {
+        let Ok(height) = i64::try_from(height) else {
+            return;
+        };
+        let mut sampled_at = self.sampled_at.lock().unwrap();
+        if sampled_at
+            .get(chain)
+            .is_some_and(|last| fetched_at - *last < SAMPLE_INTERVAL)
+        {
+            return;
+        }
+        sampled_at.insert(chain.to_string(), fetched_at);
+        self.pending.lock().unwrap().push(BlockNumberRecord {
+            chain: chain.to_string(),
+            network_type: network_type.as_str().to_string(),
+            height,
+            source_rpc: source_rpc.to_string(),
+            fetched_at,
+        });
+    }
+
+    /// Writes every buffered block number in a single insert. The batch is put back
+    /// if the write fails, so it's retried on the next flush.
+    pub async fn flush(&self) -> Result<()> {
+        let records = std::mem::take(&mut *self.pending.lock().unwrap());
+        if records.is_empty() {
+            return Ok(());
+        }
+
+        let result = sqlx::query(
+            "
+            INSERT INTO block_number_history (chain, network_type, height, source_rpc, fetched_at)
+            SELECT * FROM UNNEST($1::TEXT[], $2::TEXT[], $3::BIGINT[], $4::TEXT[], $5::TIMESTAMPTZ[])
+        ",
+        )
+        .bind(records.iter().map(|r| r.chain.as_str()).collect::<Vec<_>>())
+        .bind(records.iter().map(|r| r.network_type.as_str()).collect::<Vec<_>>())
+        .bind(records.iter().map(|r| r.height).collect::<Vec<_>>())
+        .bind(records.iter().map(|r| r.source_rpc.as_str()).collect::<Vec<_>>())
+        .bind(records.iter().map(|r| r.fetched_at).collect::<Vec<_>>())
+        .execute(&self.pool)
+        .await;
+
+        if let Err(e) = result {
+            let mut pending = self.pending.lock().unwrap();
+            let newer = std::mem::replace(&mut *pending, records);
+            pending.extend(newer);
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    /// Deletes the block numbers fetched before `before`.
+    pub async fn prune(&self, before: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM block_number_history WHERE fetched_at < $1")
+            .bind(before)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Returns the most recently stored block number of every chain and network.
+    pub async fn get_latest_block_numbers(&self) -> Result<Vec<BlockNumberRecord>> {
+        let records = sqlx::query_as::<_, BlockNumberRecord>(
+            "
+            SELECT DISTINCT ON (chain, network_type)
+                chain, network_type, height, source_rpc, fetched_at
+            FROM block_number_history
+            ORDER BY chain, network_type, fetched_at DESC
+        ",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(records)
+    }
+
+    /// Returns the latest block numbers stored for `chain` between `from` and `to`,
+    /// oldest first.
+    pub async fn get_block_number_history(
+        &self,
+        chain: &str,
+        network_type: &NetworkType,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<BlockNumberRecord>> {
+        let mut records = sqlx::query_as::<_, BlockNumberRecord>(
+            "
+            SELECT chain, network_type, height, source_rpc, fetched_at
+            FROM block_number_history
+            WHERE chain = $1 AND network_type = $2 AND fetched_at BETWEEN $3 AND $4
+            ORDER BY fetched_at DESC
+            LIMIT $5
+        ",
+        )
+        .bind(chain)
+        .bind(network_type.as_str())
+        .bind(from)
+        .bind(to)
+        .bind(MAX_HISTORY_ROWS)
+        .fetch_all(&self.pool)
+        .await?;
+        records.reverse();
+
+        Ok(records)
+    }
+}
@@ -0,0 +1,134 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// An amount of a filler's liquidity reserved for an in-flight swap. A commitment
+/// stops counting against the balance once released or once its chain passes
+/// `expires_at_block`.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct Commitment {
+    pub id: String,
+    pub chain: String,
+    pub asset: String,
+    pub amount: String,
+    #[serde(rename = "expiresAtBlock")]
+    pub expires_at_block: i64,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    #[serde(rename = "releasedAt", skip_serializing_if = "Option::is_none")]
+    pub released_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewCommitment {
+    pub chain: String,
+    pub asset: String,
+    pub amount: String,
+}
+
+pub struct CommitmentRepo {
+    pool: PgPool,
+}
+
+impl CommitmentRepo {
+    pub async fn new(pool: PgPool) -> Result<Self> {
+        // amounts are stored as text since they may not fit in a BIGINT
+        sqlx::query(
+            "
+            CREATE TABLE IF NOT EXISTS commitments (
+                id UUID PRIMARY KEY,
+                chain TEXT NOT NULL,
+                asset TEXT NOT NULL,
+                amount TEXT NOT NULL,
+                expires_at_block BIGINT NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL,
+                released_at TIMESTAMPTZ
+            )
+        ",
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query(
+            "
+            CREATE INDEX IF NOT EXISTS commitments_active_idx
+            ON commitments (chain)
+            WHERE released_at IS NULL
+        ",
+        )
+        .execute(&pool)
+        .await?;
+
+        Ok(Self { pool })
+    }
+
+    pub async fn create_commitment(
+        &self,
+        commitment: NewCommitment,
+        expires_at_block: u64,
+    ) -> Result<Commitment> {
+        let commitment = sqlx::query_as::<_, Commitment>(
+            "
+            INSERT INTO commitments (id, chain, asset, amount, expires_at_block, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id::TEXT, chain, asset, amount, expires_at_block, created_at, released_at
+        ",
+        )
+        .bind(Uuid::new_v4())
+        .bind(&commitment.chain)
+        .bind(&commitment.asset)
+        .bind(&commitment.amount)
+        .bind(i64::try_from(expires_at_block)?)
+        .bind(Utc::now())
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(commitment)
+    }
+
+    pub async fn release_commitment(&self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            "
+            UPDATE commitments
+            SET released_at = $1
+            WHERE id = $2 AND released_at IS NULL
+        ",
+        )
+        .bind(Utc::now())
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Returns the unreleased commitments that haven't expired yet, given the current
+    /// block number of each chain in `block_numbers`.
+    pub async fn get_active_commitments(
+        &self,
+        block_numbers: &[(String, u64)],
+    ) -> Result<Vec<Commitment>> {
+        let (chains, block_numbers): (Vec<&str>, Vec<i64>) = block_numbers
+            .iter()
+            .map(|(chain, block_number)| (chain.as_str(), *block_number as i64))
+            .unzip();
+        let commitments = sqlx::query_as::<_, Commitment>(
+            "
+            SELECT c.id::TEXT, c.chain, c.asset, c.amount, c.expires_at_block, c.created_at,
+                c.released_at
+            FROM commitments c
+            JOIN UNNEST($1::TEXT[], $2::BIGINT[]) AS tip (chain, block_number)
+                ON c.chain = tip.chain
+            WHERE c.released_at IS NULL AND c.expires_at_block > tip.block_number
+        ",
+        )
+        .bind(chains)
+        .bind(block_numbers)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(commitments)
+    }
+}
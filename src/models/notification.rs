@@ -2,9 +2,10 @@ use anyhow::Result;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::{postgres::PgQueryResult, PgPool};
+use tokio::sync::broadcast;
 use uuid::Uuid;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Notification {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub id: Option<String>,
@@ -16,15 +17,23 @@ pub struct Notification {
     pub updated_at: Option<DateTime<Utc>>,
 }
 
+/// Emitted after every successful write to the notifications table.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+pub enum NotificationEvent {
+    CREATED { notification: Notification },
+    UPDATED { notification: Notification },
+    LATEST { id: String },
+}
+
 pub struct NotificationRepo {
     pool: PgPool,
+    pub updates: broadcast::Sender<NotificationEvent>,
 }
 
 impl NotificationRepo {
-    pub async fn new() -> Result<Self> {
-        let uri = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-        let pool = PgPool::connect(&uri).await?;
-
+    pub async fn new(pool: PgPool) -> Result<Self> {
         // Create table if not exists
         sqlx::query(
             "
@@ -41,7 +50,10 @@ impl NotificationRepo {
         .execute(&pool)
         .await?;
 
-        Ok(Self { pool })
+        Ok(Self {
+            pool,
+            updates: broadcast::channel(64).0,
+        })
     }
 
     pub async fn create_notification(&self, mut notification: Notification) -> Result<()> {
@@ -54,7 +66,7 @@ impl NotificationRepo {
             VALUES ($1, $2, $3, $4, $5, $6)
         ",
         )
-        .bind(Uuid::parse_str(&notification.id.unwrap())?)
+        .bind(Uuid::parse_str(notification.id.as_ref().unwrap())?)
         .bind(&notification.title)
         .bind(&notification.description)
         .bind(&notification.image)
@@ -63,6 +75,10 @@ impl NotificationRepo {
         .execute(&self.pool)
         .await?;
 
+        // sending only fails when nobody is subscribed
+        let _ = self
+            .updates
+            .send(NotificationEvent::CREATED { notification });
         Ok(())
     }
 
@@ -134,12 +150,14 @@ impl NotificationRepo {
         Ok(notifications)
     }
 
-    pub async fn update_notification(&self, notification: Notification) -> Result<bool> {
+    pub async fn update_notification(&self, mut notification: Notification) -> Result<bool> {
         let id = notification
             .id
             .clone()
             .ok_or_else(|| anyhow::anyhow!("Notification id must be set for update operation"))?;
 
+        let updated_at = Utc::now();
+
         let result: PgQueryResult = sqlx::query(
             "
             UPDATE notifications
@@ -151,12 +169,19 @@ impl NotificationRepo {
         .bind(&notification.description)
         .bind(&notification.image)
         .bind(&notification.link)
-        .bind(Utc::now())
+        .bind(updated_at)
         .bind(Uuid::parse_str(&id)?)
         .execute(&self.pool)
         .await?;
 
-        Ok(result.rows_affected() > 0)
+        let updated = result.rows_affected() > 0;
+        if updated {
+            notification.updated_at = Some(updated_at);
+            let _ = self
+                .updates
+                .send(NotificationEvent::UPDATED { notification });
+        }
+        Ok(updated)
     }
 
     pub async fn set_latest_notification(&self, id: &str) -> Result<bool> {
@@ -172,9 +197,16 @@ impl NotificationRepo {
         .execute(&self.pool)
         .await?;
 
-        Ok(result.rows_affected() > 0)
+        let updated = result.rows_affected() > 0;
+        if updated {
+            let _ = self
+                .updates
+                .send(NotificationEvent::LATEST { id: id.to_string() });
+        }
+        Ok(updated)
     }
 
+    #[allow(dead_code)]
     pub async fn delete_notification(&self, id: &str) -> Result<bool> {
         let result = sqlx::query(
             "
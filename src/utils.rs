@@ -1,9 +1,14 @@
-use std::{collections::HashMap, fs};
+use std::{collections::HashMap, env, fs};
 
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
-use crate::models::assets::Network;
+use crate::{
+    chains::bitcoin::AddressFormat,
+    models::assets::{Network, MIN_POLL_INTERVAL_MS},
+};
 
+#[allow(dead_code)]
 #[derive(Deserialize, Serialize)]
 pub struct ConfigData {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -13,6 +18,13 @@ pub struct ConfigData {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub localnet: Option<Network>,
 }
+
+/// Reads the networks from `config.json`. `${VAR}` placeholders in any string are
+/// replaced by the `VAR` env var, or by the contents of the file named by `VAR_FILE`
+/// (e.g. a docker secret) when `VAR` is unset; `$${` is kept as a literal `${`.
+/// Networks whose placeholders can't be resolved, with a `primaryHeight` their family
+/// can't publish, with a `staleAfterSecs` not above their poll interval, or with
+/// filler addresses that don't belong to the chain, are left out.
 pub fn load_config() -> HashMap<String, Network> {
     let config_file = "config.json";
     let config_str = match fs::read_to_string(config_file) {
@@ -23,7 +35,7 @@ pub fn load_config() -> HashMap<String, Network> {
         }
     };
 
-    let config: HashMap<String, Network> = match serde_json::from_str(&config_str) {
+    let config: HashMap<String, Value> = match serde_json::from_str(&config_str) {
         Ok(parsed) => parsed,
         Err(e) => {
             eprintln!("Error parsing config JSON: {}", e);
@@ -31,14 +43,140 @@ pub fn load_config() -> HashMap<String, Network> {
         }
     };
 
-    let parsed_config: HashMap<String, Network> = config
-        .iter()
-        .map(|(key, network)| (key.clone(), network.clone()))
-        .collect();
+    let mut parsed_config = HashMap::new();
+    for (key, entry) in config {
+        let network = resolve_env(entry.clone()).and_then(|resolved| {
+            serde_json::from_value::<Network>(resolved).map_err(|_| {
+                // report the error of the unresolved entry, since resolved values may
+                // be secrets
+                serde_json::from_value::<Network>(entry)
+                    .err()
+                    .map_or("invalid value".to_string(), |e| e.to_string())
+            })
+        });
+        match network
+            .map(|network| clamp_poll_interval(&key, network))
+            .and_then(check_primary_height)
+            .and_then(check_stale_after)
+            .and_then(normalize_filler_addresses)
+        {
+            Ok(network) => {
+                parsed_config.insert(key, network);
+            }
+            Err(e) => eprintln!("Error parsing config of network: {} {}", key, e),
+        }
+    }
 
     parsed_config
 }
 
+/// Rejects a `primaryHeight` the chain family can't publish, rather than ignoring it.
+fn check_primary_height(network: Network) -> Result<Network, String> {
+    match network.primary_height {
+        Some(height) if !network.chain_family.primary_heights().contains(&height) => Err(format!(
+            "primaryHeight {} is not supported by chainFamily {}",
+            format!("{:?}", height).to_lowercase(),
+            format!("{:?}", network.chain_family).to_lowercase()
+        )),
+        _ => Ok(network),
+    }
+}
+
+/// Raises a `pollIntervalMs` below the minimum to it, so the interval checked against
+/// `staleAfterSecs` is the one polled at.
+fn clamp_poll_interval(key: &str, mut network: Network) -> Network {
+    if network.poll_interval_ms < MIN_POLL_INTERVAL_MS {
+        eprintln!(
+            "pollIntervalMs {} of network: {} is below the minimum, polling every {}ms",
+            network.poll_interval_ms, key, MIN_POLL_INTERVAL_MS
+        );
+        network.poll_interval_ms = MIN_POLL_INTERVAL_MS;
+    }
+    network
+}
+
+/// Rejects a `staleAfterSecs` that doesn't leave room for a single poll, which would
+/// report the chain stale between fetches and drop its websockets as idle.
+fn check_stale_after(network: Network) -> Result<Network, String> {
+    match network.stale_after_secs {
+        Some(secs) if secs.saturating_mul(1000) <= network.poll_interval_ms => Err(format!(
+            "staleAfterSecs {} must be longer than pollIntervalMs {}",
+            secs, network.poll_interval_ms
+        )),
+        _ => Ok(network),
+    }
+}
+
+/// Checks the filler addresses of UTXO chains against the chain and network, and
+/// rewrites them in the form sent to the rpcs.
+fn normalize_filler_addresses(mut network: Network) -> Result<Network, String> {
+    if let Some(format) = AddressFormat::for_network(&network) {
+        network.filler_addresses = network
+            .filler_addresses
+            .iter()
+            .map(|address| format.normalize(address).map_err(|e| e.to_string()))
+            .collect::<Result<_, _>>()?;
+    }
+    Ok(network)
+}
+
+/// Replaces the `${VAR}` placeholders in every string of `value`.
+fn resolve_env(value: Value) -> Result<Value, String> {
+    Ok(match value {
+        Value::String(s) => Value::String(interpolate(&s)?),
+        Value::Array(values) => Value::Array(
+            values
+                .into_iter()
+                .map(resolve_env)
+                .collect::<Result<_, _>>()?,
+        ),
+        Value::Object(entries) => Value::Object(
+            entries
+                .into_iter()
+                .map(|(key, value)| Ok((key, resolve_env(value)?)))
+                .collect::<Result<_, String>>()?,
+        ),
+        value => value,
+    })
+}
+
+fn interpolate(s: &str) -> Result<String, String> {
+    let mut resolved = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find('$') {
+        resolved.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(escaped) = rest.strip_prefix("$${") {
+            resolved.push_str("${");
+            rest = escaped;
+        } else if let Some(placeholder) = rest.strip_prefix("${") {
+            let end = placeholder.find('}').ok_or("unterminated ${ placeholder")?;
+            resolved.push_str(&env_value(&placeholder[..end])?);
+            rest = &placeholder[end + 1..];
+        } else {
+            resolved.push('$');
+            rest = &rest[1..];
+        }
+    }
+    resolved.push_str(rest);
+    Ok(resolved)
+}
+
+/// Value of the `name` env var, falling back to the contents of the file named by
+/// `name_FILE`. Errors only mention variable names, never values.
+fn env_value(name: &str) -> Result<String, String> {
+    if let Ok(value) = env::var(name) {
+        return Ok(value);
+    }
+    let file_var = format!("{}_FILE", name);
+    match env::var(&file_var) {
+        Ok(path) => fs::read_to_string(path)
+            .map(|contents| contents.trim_end_matches(['\r', '\n']).to_string())
+            .map_err(|e| format!("can't read file of {}: {}", file_var, e)),
+        Err(_) => Err(format!("{} is not set (nor {})", name, file_var)),
+    }
+}
+
 #[derive(Serialize)]
 pub struct ApiResponse<T> {
     status: String,
@@ -60,3 +198,141 @@ impl<T> ApiResponse<T> {
         }
     }
 }
+
+/// Checks the `authToken` header against the `AUTH_TOKEN` env var.
+pub fn is_authorized(headers: &axum::http::HeaderMap) -> bool {
+    let expected_token = env::var("AUTH_TOKEN").expect("Missing AUTH_TOKEN in .env");
+    headers
+        .get("authToken")
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|token| token == expected_token)
+}
+
+/// Host (and port) of an rpc url, safe to show since api keys usually live in the
+/// path or query string.
+pub fn rpc_host(rpc: &str) -> String {
+    match reqwest::Url::parse(rpc) {
+        Ok(url) => match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            _ => "<unknown host>".to_string(),
+        },
+        Err(_) => "<invalid url>".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::models::assets::HeightKind;
+
+    /// A minimal network config with `overrides` applied.
+    fn network(overrides: Value) -> Network {
+        let mut config = json!({
+            "chainId": "1",
+            "networkLogo": "",
+            "explorer": "",
+            "networkType": "testnet",
+            "name": "Test",
+            "assetConfig": [],
+            "rpcs": ["https://rpc.example.com"]
+        });
+        config
+            .as_object_mut()
+            .unwrap()
+            .extend(overrides.as_object().unwrap().clone());
+        serde_json::from_value(config).unwrap()
+    }
+
+    #[test]
+    fn checks_stale_after_against_the_clamped_poll_interval() {
+        let config = network(json!({ "pollIntervalMs": 10 }));
+        assert_eq!(
+            clamp_poll_interval("test", config).poll_interval_ms,
+            MIN_POLL_INTERVAL_MS
+        );
+
+        let config = network(json!({ "pollIntervalMs": 0, "staleAfterSecs": 0 }));
+        assert!(check_stale_after(clamp_poll_interval("test", config)).is_err());
+        let config = network(json!({ "pollIntervalMs": 5000, "staleAfterSecs": 5 }));
+        assert!(check_stale_after(clamp_poll_interval("test", config)).is_err());
+        let config = network(json!({ "pollIntervalMs": 5000, "staleAfterSecs": 6 }));
+        assert!(check_stale_after(clamp_poll_interval("test", config)).is_ok());
+    }
+
+    #[test]
+    fn rejects_primary_heights_the_family_cannot_publish() {
+        for (family, height, supported) in [
+            ("arbitrum", "l1", true),
+            ("opstack", "l2", true),
+            ("scroll", "l1", true),
+            ("zksync", "l2", true),
+            ("zksync", "l1", false),
+            ("evm", "l2", false),
+        ] {
+            let config = network(json!({ "chainFamily": family, "primaryHeight": height }));
+            assert_eq!(
+                check_primary_height(config).is_ok(),
+                supported,
+                "{}",
+                family
+            );
+        }
+        let config = network(json!({ "chainFamily": "zksync" }));
+        assert_eq!(
+            check_primary_height(config).unwrap().primary_height,
+            None::<HeightKind>
+        );
+        let error = check_primary_height(network(json!({ "primaryHeight": "l1" }))).unwrap_err();
+        assert_eq!(
+            error,
+            "primaryHeight l1 is not supported by chainFamily evm"
+        );
+    }
+
+    #[test]
+    fn interpolates_env_vars() {
+        env::set_var("UTILS_TEST_KEY", "secret");
+        assert_eq!(
+            interpolate("https://rpc.example.com/v2/${UTILS_TEST_KEY}?a=$1").unwrap(),
+            "https://rpc.example.com/v2/secret?a=$1"
+        );
+    }
+
+    #[test]
+    fn keeps_escaped_placeholders() {
+        assert_eq!(
+            interpolate("$${UTILS_TEST_UNSET} costs $$5").unwrap(),
+            "${UTILS_TEST_UNSET} costs $$5"
+        );
+    }
+
+    #[test]
+    fn rejects_unterminated_and_unset_placeholders() {
+        assert!(interpolate("https://rpc.example.com/${UTILS_TEST_KEY").is_err());
+        let error = interpolate("${UTILS_TEST_UNSET}").unwrap_err();
+        assert_eq!(
+            error,
+            "UTILS_TEST_UNSET is not set (nor UTILS_TEST_UNSET_FILE)"
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_file_var() {
+        let path = env::temp_dir().join(format!("utils-test-secret-{}", std::process::id()));
+        fs::write(&path, "from-file\n").unwrap();
+        env::set_var("UTILS_TEST_SECRET_FILE", &path);
+        assert_eq!(env_value("UTILS_TEST_SECRET").unwrap(), "from-file");
+
+        // the env var itself wins over the file
+        env::set_var("UTILS_TEST_PRESET", "from-env");
+        env::set_var("UTILS_TEST_PRESET_FILE", &path);
+        assert_eq!(env_value("UTILS_TEST_PRESET").unwrap(), "from-env");
+        fs::remove_file(&path).unwrap();
+
+        env::set_var("UTILS_TEST_MISSING_FILE", "/nonexistent/secret");
+        assert!(env_value("UTILS_TEST_MISSING").is_err());
+    }
+}